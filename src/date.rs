@@ -0,0 +1,115 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// calendar date in utc, used to turn DDHHMMZ groups into full timestamps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UtcDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl UtcDate {
+    //"2025-11-04" or "2025-11-04T21:00:00.000Z" (only date part is used)
+    pub fn parse(s: &str) -> Option<UtcDate>{
+        let s = s.trim();
+        let date = s.get(..10)?;
+        let mut it = date.split('-');
+        let year = it.next()?.parse::<i32>().ok()?;
+        let month = it.next()?.parse::<u32>().ok()?;
+        let day = it.next()?.parse::<u32>().ok()?;
+        if it.next().is_some() || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month){
+            return None;
+        }
+        // after the date only time part is allowed
+        if s.len() > 10 && !s[10..].starts_with(['T', ' ']){
+            return None;
+        }
+        Some(UtcDate { year, month, day })
+    }
+
+    //current date from system clock
+    pub fn today() -> UtcDate{
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        UtcDate::from_days((secs / 86_400) as i64)
+    }
+
+    //days since 1970-01-01 (civil calendar algorithm by H. Hinnant)
+    pub fn to_days(self) -> i64{
+        let y = if self.month <= 2 { self.year - 1 } else { self.year } as i64;
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let m = self.month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    pub fn from_days(z: i64) -> UtcDate{
+        let z = z + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+        UtcDate { year, month, day }
+    }
+
+    //same day-of-month in prev/this/next month, whichever is closest to self
+    //handles 312350Z seen on the 1st of next month and similar rollovers
+    pub fn resolve_day(self, day: u32) -> Option<UtcDate>{
+        let mut best: Option<UtcDate> = None;
+        for shift in [-1i32, 0, 1]{
+            let idx = self.year * 12 + self.month as i32 - 1 + shift;
+            let (year, month) = (idx.div_euclid(12), idx.rem_euclid(12) as u32 + 1);
+            if day == 0 || day > days_in_month(year, month){
+                continue;
+            }
+            let cand = UtcDate { year, month, day };
+            let dist = (cand.to_days() - self.to_days()).abs();
+            if best.is_none_or(|b| dist < (b.to_days() - self.to_days()).abs()){
+                best = Some(cand);
+            }
+        }
+        best
+    }
+
+    //iso-8601 utc timestamp for this date
+    pub fn iso(self, hour: u32, minute: u32) -> String{
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:00Z", self.year, self.month, self.day, hour, minute)
+    }
+
+    //minutes since 1970-01-01 00:00Z, hour 24 simply rolls into next day
+    pub fn minutes(self, hour: u32, minute: u32) -> i64{
+        self.to_days() * 1440 + hour as i64 * 60 + minute as i64
+    }
+}
+
+//minutes since epoch => iso-8601 utc timestamp
+pub fn iso_minutes(m: i64) -> String{
+    let r = m.rem_euclid(1440);
+    UtcDate::from_days(m.div_euclid(1440)).iso((r / 60) as u32, (r % 60) as u32)
+}
+
+//iso-8601 utc timestamp (2025-11-04T20:53:00Z, seconds ignored) => minutes since epoch
+pub fn parse_iso_minutes(s: &str) -> Option<i64>{
+    let date = UtcDate::parse(s)?;
+    let hour = s.get(11..13)?.parse::<u32>().ok()?;
+    let minute = s.get(14..16)?.parse::<u32>().ok()?;
+    Some(date.minutes(hour, minute))
+}
+
+fn is_leap(y: i32) -> bool{
+    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
+}
+
+pub fn days_in_month(y: i32, m: u32) -> u32{
+    match m{
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 => if is_leap(y) { 29 } else { 28 },
+        _ => 0,
+    }
+}
@@ -1,7 +1,14 @@
 #![doc = include_str!("../docs.md")]
 
+mod date;
 mod metar;
-pub use metar::{Rule, SiftParser};
+mod pirep;
+mod taf;
+mod verify;
+pub use date::UtcDate;
+pub use metar::{Rule, SiftParser, decode_metar, decode_metar_at};
+pub use pirep::decode_pirep;
+pub use taf::{Taf, TafForecast, TafPeriod, decode_taf, decode_taf_at, parse_taf};
 use csv::WriterBuilder;
 use serde_json::Value;
 use std::collections::{BTreeSet, HashMap};
@@ -24,6 +31,12 @@ pub fn parse_json(s: &str) -> Result<Value, ParseError>{
 
 // main logic == flatten json =>rows=>csv
 pub fn convert_to_csv(v: &Value)->Result<String, ParseError>{
+    convert_to_csv_at(v, None)
+}
+
+// same as convert_to_csv, but DDHHMMZ times are resolved against given date
+// per-object reportTime/receiptTime still wins, None == today
+pub fn convert_to_csv_at(v: &Value, reference: Option<UtcDate>)->Result<String, ParseError>{
     //hashing all rows
     let mut rows = Vec::<HashMap<String, String>>::new();
     //using tree set to keep keys sorted + uniqe
@@ -34,7 +47,7 @@ pub fn convert_to_csv(v: &Value)->Result<String, ParseError>{
             for it in a{
                 // new flat map for each element
                 let mut m= HashMap::new();
-                flatten(it, "".into(), &mut m)?;
+                flatten(it, "".into(), reference, &mut m)?;
                 // remember all col names from row
                 for k in m.keys()
                 {keys.insert(k.clone()); // clone because set owns the Str
@@ -46,7 +59,7 @@ pub fn convert_to_csv(v: &Value)->Result<String, ParseError>{
         Value::Object(_)=>{
             //same as above but only once
             let mut m =HashMap::new();
-            flatten(v, "".into(), &mut m)?;
+            flatten(v, "".into(), reference, &mut m)?;
             //col names
             for k in m.keys(){
                 keys.insert(k.clone());
@@ -56,6 +69,78 @@ pub fn convert_to_csv(v: &Value)->Result<String, ParseError>{
         // everything else == err
         _ =>return Err(ParseError::Structure("expect object or array".into())),
     }
+    write_csv(rows, keys)
+}
+
+// taf mode: every taf string found in json gives one row per forecast period
+// (base forecast + each FM/BECMG/TEMPO/PROB group), other json fields are not included
+pub fn convert_taf_periods_to_csv(v: &Value, reference: Option<UtcDate>)->Result<String, ParseError>{
+    let tafs = collect_tafs(v, reference)?;
+    let rows: Vec<HashMap<String, String>> = tafs.iter().flat_map(Taf::period_rows).collect();
+    // no header to write, better tell than print empty csv
+    if rows.is_empty(){
+        return Err(ParseError::Structure("no TAF found".into()));
+    }
+    let keys = rows.iter().flat_map(|r| r.keys().cloned()).collect();
+    write_csv(rows, keys)
+}
+
+// taf hourly mode: one row per station-hour of validity with forecast in force at that hour
+// FM/BECMG are applied, TEMPO/PROB go to alt[n].* columns
+pub fn convert_taf_timeline_to_csv(v: &Value, reference: Option<UtcDate>)->Result<String, ParseError>{
+    let tafs = collect_tafs(v, reference)?;
+    let rows: Vec<HashMap<String, String>> = tafs.iter().flat_map(Taf::timeline).collect();
+    if rows.is_empty(){
+        return Err(ParseError::Structure("no TAF found".into()));
+    }
+    let keys = rows.iter().flat_map(|r| r.keys().cloned()).collect();
+    write_csv(rows, keys)
+}
+
+// verify mode: each metar in metars json vs taf in force at its obs_time (from tafs json)
+// returns (per-observation csv, per-station summary csv)
+pub fn verify_to_csv(metars: &Value, tafs: &Value, reference: Option<UtcDate>)->Result<(String, String), ParseError>{
+    let metars = collect_reports(metars, reference, metar::decode_metar_at)?;
+    let tafs = collect_tafs(tafs, reference)?;
+    let (rows, summary) = verify::verify_rows(&metars, &tafs);
+    let keys = rows.iter().flat_map(|r| r.keys().cloned()).collect();
+    let summary_keys = summary.iter().flat_map(|r| r.keys().cloned()).collect();
+    Ok((write_csv(rows, keys)?, write_csv(summary, summary_keys)?))
+}
+
+// all str values that decode as taf, in json order
+fn collect_tafs(v: &Value, reference: Option<UtcDate>)->Result<Vec<Taf>, ParseError>{
+    collect_reports(v, reference, taf::parse_taf)
+}
+
+// all str values that decode, in json order
+// issueTime/reportTime (or other reference field) next to the string is used same way as in flatten
+fn collect_reports<T>(v: &Value, reference: Option<UtcDate>, decode: fn(&str, Option<UtcDate>) -> Option<T>)->Result<Vec<T>, ParseError>{
+    fn walk<T>(v: &Value, reference: Option<UtcDate>, decode: fn(&str, Option<UtcDate>) -> Option<T>, out: &mut Vec<T>){
+        match v{
+            Value::Object(m)=>{
+                let reference = REFERENCE_FIELDS.iter()
+                    .find_map(|f| m.get(*f).and_then(Value::as_str).and_then(UtcDate::parse))
+                    .or(reference);
+                for vv in m.values(){
+                    walk(vv, reference, decode, out);
+                }
+            }
+            Value::Array(a)=>a.iter().for_each(|vv| walk(vv, reference, decode, out)),
+            Value::String(s)=>out.extend(decode(s, reference)),
+            _ => {}
+        }
+    }
+    if !matches!(v, Value::Array(_) | Value::Object(_)){
+        return Err(ParseError::Structure("expect object or array".into()));
+    }
+    let mut out = Vec::new();
+    walk(v, reference, decode, &mut out);
+    Ok(out)
+}
+
+// rows => csv text, header is all keys (sorted)
+fn write_csv(rows: Vec<HashMap<String, String>>, keys: BTreeSet<String>)->Result<String, ParseError>{
     // starting a header row from all keys
     let hdr: Vec<String> =keys.into_iter().collect();
 
@@ -83,12 +168,26 @@ pub fn convert_to_csv(v: &Value)->Result<String, ParseError>{
 //if true == metar with be in col name
 const PREFIX_WITH_DETECTOR_NAME: bool =true;
 
+//full-string decoder: whole str => flat map, None if it's not that kind of report
+type Detector = fn(&str, Option<UtcDate>) -> Option<HashMap<String, String>>;
+//tried in order, first hit wins
+//taf goes first: metar grammar would take its validity and change groups as unparsed
+const DETECTORS: [(&str, Detector); 3] = [("taf", taf::decode_taf_at), ("metar", metar::decode_metar_at), ("pirep", pirep::decode_pirep_at)];
+
+//sibling fields that tell which date the report belongs to (aviationweather api)
+const REFERENCE_FIELDS: [&str; 3] = ["reportTime", "receiptTime", "issueTime"];
+
 // flatten json recursively
 //v is curr val
 //prefix is col name prefix
-fn flatten(v: &Value, prefix:String,out:&mut HashMap<String, String>)->Result<(), ParseError>{
+//reference is date for DDHHMMZ groups, inherited from parents
+fn flatten(v: &Value, prefix:String, reference: Option<UtcDate>, out:&mut HashMap<String, String>)->Result<(), ParseError>{
     match v{
         Value::Object(m)=>{
+            // reportTime next to rawOb is better than anything from outside
+            let reference = REFERENCE_FIELDS.iter()
+                .find_map(|f| m.get(*f).and_then(Value::as_str).and_then(UtcDate::parse))
+                .or(reference);
             for (k, vv) in m{
                 //if prefix empty, key is k, else prefix.k
                 let key = if prefix.is_empty(){
@@ -96,16 +195,16 @@ fn flatten(v: &Value, prefix:String,out:&mut HashMap<String, String>)->Result<()
                 } else{
                     format!("{prefix}.{k}")
                 };
-                flatten(vv, key, out)?;
+                flatten(vv, key, reference, out)?;
             }
         }
         Value::Array(a)=>{
             //if arr = [v0,v1] then cols [pref[0], pref[1]]
             for (i, vv) in a.iter().enumerate(){
-                flatten(vv, format!("{}[{}]", prefix, i), out)?;
+                flatten(vv, format!("{}[{}]", prefix, i), reference, out)?;
             }
         }
-        Value::String(s)=>parse_scalar(prefix, s, out)?,
+        Value::String(s)=>parse_scalar(prefix, s, reference, out)?,
         Value::Number(n) =>{
             out.insert(prefix, n.to_string());
         }
@@ -120,16 +219,17 @@ fn flatten(v: &Value, prefix:String,out:&mut HashMap<String, String>)->Result<()
 }
 
 //scalar pipeline for str val
-fn parse_scalar(prefix: String,s:&str,out:&mut HashMap<String, String>,)->Result<(), ParseError>{
+fn parse_scalar(prefix: String,s:&str, reference: Option<UtcDate>, out:&mut HashMap<String, String>,)->Result<(), ParseError>{
     let text = s.trim();
     if text.is_empty(){
         out.insert(prefix, String::new());
         return Ok(());
     }
-    //full-string detectors (only metar for now)
-    if let Some(mut decoded)=metar::decode_metar(text){
-        //if metar parse ok
-        let det_name="metar";
+    //full-string detectors
+    for (det_name, decode) in DETECTORS{
+        let Some(mut decoded) = decode(text, reference) else{
+            continue;
+        };
         for (dk, dv) in decoded.drain(){
             //drain() puts out decoded key-values
             // build column name with/without detector prefix
@@ -139,8 +239,8 @@ fn parse_scalar(prefix: String,s:&str,out:&mut HashMap<String, String>,)->Result
                 } else{dk}
             } else if PREFIX_WITH_DETECTOR_NAME{
                 format!("{prefix}.{det_name}.{dk}")
-            } else
-            {format!("{prefix}.{dk}")
+            } else {
+                format!("{prefix}.{dk}")
             };
             out.insert(col,dv);//insert in out map
         }
@@ -158,6 +258,7 @@ fn parse_scalar(prefix: String,s:&str,out:&mut HashMap<String, String>,)->Result
         let t = tokens[0].trim();
         if let Some(pat) = metar::holds_pattern_value(t) 
         { metar::apply_pattern(&prefix, t, pat, out);
+            metar::resolve_obs_time(&prefix, reference, out);
         } else{
             out.insert(prefix,text.to_string());
         }
@@ -186,5 +287,7 @@ fn parse_scalar(prefix: String,s:&str,out:&mut HashMap<String, String>,)->Result
         out.insert(col, t.to_string());
         i += 1;
     }
+    // time token => full timestamp, same as for decoded metar
+    metar::resolve_obs_time(&prefix, reference, out);
     Ok(())
 }
@@ -1,7 +1,8 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use json_sift_parser::{convert_to_csv, parse_json}; //, print_structure};
-use std::{fs, fs::File, io::Write, path::PathBuf};
+use clap::{Parser, Subcommand, ValueEnum};
+use json_sift_parser::{UtcDate, convert_taf_periods_to_csv, convert_taf_timeline_to_csv, convert_to_csv_at, parse_json, verify_to_csv}; //, print_structure};
+use serde_json::Value;
+use std::{fs, fs::File, io::Write, path::{Path, PathBuf}};
 
 #[derive(Parser, Debug)]
 #[command(name = "jsonsift", version = "1.0", about = "JsonSift is my first parser. It processes aviation weather METAR data used in civil flights")]
@@ -16,24 +17,65 @@ enum Cmd {
     {    file: PathBuf,
         #[arg(short, long)]
         output: Option<PathBuf>,
+        // reportTime next to the report still wins over this
+        #[arg(long, help = "Date (YYYY-MM-DD) for DDHHMMZ groups, default is today")]
+        reference_date: Option<String>,
+        #[arg(long, value_enum, default_value_t = TafMode::Flat, help = "flat: one row per json entry, periods: one row per TAF forecast period, hourly: forecast in force for every TAF hour")]
+        taf_mode: TafMode,
+    },
+    // metar observations vs taf forecasts
+    Verify
+    {   metars: PathBuf,
+        tafs: PathBuf,
+        #[arg(short, long, help = "Per-observation CSV, default is stdout")]
+        output: Option<PathBuf>,
+        #[arg(long, help = "Per-station summary CSV, default is stderr")]
+        summary: Option<PathBuf>,
+        #[arg(long, help = "Date (YYYY-MM-DD) for DDHHMMZ groups, default is today")]
+        reference_date: Option<String>,
     },
     Credits,
 }
 
+//how TAF strings become rows
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TafMode {
+    Flat,
+    Periods,
+    Hourly,
+}
+
 fn main() -> Result<()>{
     let cli = Cli::parse();
     match cli.cmd{
-        Cmd::Decode { file, output } => {
-            let s = fs::read_to_string(&file).with_context(||format!("read {:?}", file))?;
-            let json = parse_json(&s)?;
-            let csv = convert_to_csv(&json)?;
+        Cmd::Decode { file, output, reference_date, taf_mode } => {
+            let reference = parse_reference(reference_date)?;
+            let json = read_json(&file)?;
+            let csv = match taf_mode{
+                TafMode::Flat => convert_to_csv_at(&json, reference)?,
+                TafMode::Periods => convert_taf_periods_to_csv(&json, reference)?,
+                TafMode::Hourly => convert_taf_timeline_to_csv(&json, reference)?,
+            };
+            if let Some(p) = output {
+                save(&p, &csv)?;
+            } else {
+                print!("{csv}");
+            }
+        }
+        Cmd::Verify { metars, tafs, output, summary, reference_date } => {
+            let reference = parse_reference(reference_date)?;
+            let (rows, stats) = verify_to_csv(&read_json(&metars)?, &read_json(&tafs)?, reference)?;
             if let Some(p) = output {
-                let mut f = File::create(&p).with_context(||format!("create {:?}", p))?;
-                f.write_all(csv.as_bytes())?;
-                println!("saved: {:?}", p);
-            } else 
-            {print!("{csv}");
-           }
+                save(&p, &rows)?;
+            } else {
+                print!("{rows}");
+            }
+            // summary to file or stderr, so stdout stays one csv
+            if let Some(p) = summary {
+                save(&p, &stats)?;
+            } else {
+                eprint!("{stats}");
+            }
         }
         Cmd::Credits =>{
             println!("json_sift_parser");
@@ -44,3 +86,24 @@ fn main() -> Result<()>{
         }
     }Ok(())
 }
+
+// --reference-date value => date
+fn parse_reference(d: Option<String>) -> Result<Option<UtcDate>>{
+    match d{
+        Some(d) => Ok(Some(UtcDate::parse(&d).with_context(||format!("bad --reference-date {d:?}, expected YYYY-MM-DD"))?)),
+        None => Ok(None),
+    }
+}
+
+fn read_json(file: &Path) -> Result<Value>{
+    let s = fs::read_to_string(file).with_context(||format!("read {:?}", file))?;
+    Ok(parse_json(&s)?)
+}
+
+fn save(p: &Path, csv: &str) -> Result<()>{
+    let mut f = File::create(p).with_context(||format!("create {:?}", p))?;
+    f.write_all(csv.as_bytes())?;
+    // stderr, stdout may carry the other csv
+    eprintln!("saved: {:?}", p);
+    Ok(())
+}
@@ -1,24 +1,57 @@
 use pest::Parser;
 use pest_derive::Parser;
 use std::collections::HashMap;
+use crate::date::UtcDate;
 // metar grammar via pest, see grammar.pest
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
 pub struct SiftParser;
 //part for detecting known patts
 #[derive(Clone, Copy)]
-pub enum SimplePattern {TempDew, Wind,Pressure,Time,Visibility,Cloud, FlightCategory,}//simple patts
+pub enum SimplePattern {TempDew, Wind,WindVariation,Pressure,Time,Visibility,VisibilityMetric,VisibilityMin,Cavok,Rvr,Cloud,VerticalVisibility,Weather,RecentWeather,WindShear,RunwayState,SeaState,ColourState, FlightCategory,}//simple patts
 
 // parse full metar string via pest into flat map
+//groups have fixed positions, so station is only taken from its own slot
+//obs_time is resolved against current date, see decode_metar_at
 pub fn decode_metar(s: &str) -> Option<HashMap<String, String>>{
-    let pairs = SiftParser::parse(Rule::metar_report, s).ok()?;//trying to parse via pest
+    decode_metar_at(s, None)
+}
+
+//same as decode_metar, day-of-month in time group is resolved against reference date
+pub fn decode_metar_at(s: &str, reference: Option<UtcDate>) -> Option<HashMap<String, String>>{
+    let s = norm(s); // drop trailing '=' and double spaces
+    let pairs = SiftParser::parse(Rule::metar_report, &s).ok()?;//trying to parse via pest
     let mut out = HashMap::new();
     for p in pairs {
-        visit_metar(&p, &mut out);
+        visit_metar(&p, "", &mut out);
     }
+    resolve_obs_time("", reference, &mut out);
     if out.is_empty(){ None } else { Some(out) }//return decoded/none
 }
 
+//obs_day/obs_hour/obs_minute => obs_time as iso-8601 utc
+//month and year come from reference (or today), with month/year rollover
+pub fn resolve_obs_time(prefix: &str, reference: Option<UtcDate>, out: &mut HashMap<String, String>){
+    let col = |name: &str| if prefix.is_empty(){ name.to_string() } else { format!("{prefix}.{name}") };
+    let num = |name: &str| out.get(&col(name)).and_then(|v| v.parse::<u32>().ok());
+    let (Some(day), Some(hour), Some(minute)) = (num("obs_day"), num("obs_hour"), num("obs_minute")) else{
+        return;
+    };
+    if hour > 24 || minute > 59 || (hour == 24 && minute != 0){
+        return;
+    }
+    let reference = reference.unwrap_or_else(UtcDate::today);
+    if let Some(date) = reference.resolve_day(day){
+        // 2400Z == midnight of next day
+        let ts = if hour == 24{
+            UtcDate::from_days(date.to_days() + 1).iso(0, 0)
+        } else{
+            date.iso(hour, minute)
+        };
+        out.insert(col("obs_time"), ts);
+    }
+}
+
 //split and simple patterns
 pub fn complex_key_value(s:&str)->Vec<String>{
     let s = s.trim();
@@ -55,17 +88,22 @@ pub fn holds_pattern_value(t:&str)->Option<SimplePattern>{
     let t = t.trim();
     if t.is_empty(){return None;}
 
-    if t.contains('/') && t.len()<=6 && t.split('/').count() ==2{
+    // both halves must look like temperatures, so W15/S3 or 1/2 are not taken
+    if split_temp_dew(t).is_some(){
         return Some(SimplePattern::TempDew);
     }
 
-    if t.ends_with("KT") && t.len()>= 5{
-        let core = &t[..t.len()- 2];
-        if core.len()>=5 && core[..3].chars().all(|c|c.is_ascii_digit()){
+    if let Some(unit) = WIND_UNITS.iter().find(|u| t.ends_with(*u)){
+        let core = &t[..t.len()- unit.len()];
+        if core.len()>=5 && (core.starts_with("VRB") || core.starts_with("///") || core.get(..3).is_some_and(|d| d.bytes().all(|b| b.is_ascii_digit()))){
             return Some(SimplePattern::Wind);
     }
     }
-    if t.starts_with('A')&& t.len() == 5&& t[1..].chars().all(|c| c.is_ascii_digit()){
+    // 240V300
+    if t.len() == 7 && t.as_bytes()[3] == b'V' && t[..3].chars().chain(t[4..].chars()).all(|c| c.is_ascii_digit()){
+        return Some(SimplePattern::WindVariation);
+    }
+    if (t.starts_with('A') || t.starts_with('Q'))&& t.len() == 5&& (t[1..].chars().all(|c| c.is_ascii_digit()) || &t[1..] == "////"){
         return Some(SimplePattern::Pressure);
     }
     if t.ends_with('Z')&& t.len() == 7 && t[..6].chars().all(|c| c.is_ascii_digit()){
@@ -74,14 +112,37 @@ pub fn holds_pattern_value(t:&str)->Option<SimplePattern>{
     if t.ends_with("SM"){
         return Some(SimplePattern::Visibility);
     }
+    // no metric visibility here: 9999 or 1500W alone could be anything, it's decoded only inside full report
     // clouds
     if t.starts_with("BKN") || t.starts_with("SCT") || t.starts_with("FEW") || t.starts_with("OVC")
     {
         return Some(SimplePattern::Cloud);
     }
-    if t == "CLR" || t == "SKC"{
+    if t == "CLR" || t == "SKC" || t == "NSC" || t == "NCD"{
         return Some(SimplePattern::Cloud);
     }
+    if t.starts_with("VV") && SiftParser::parse(Rule::vertical_visibility, t).is_ok(){
+        return Some(SimplePattern::VerticalVisibility);
+    }
+    if t == "CAVOK"{
+        return Some(SimplePattern::Cavok);
+    }
+    if t.starts_with('R') && SiftParser::parse(Rule::rvr, t).is_ok(){
+        return Some(SimplePattern::Rvr);
+    }
+    // weather groups, 2-letter ones like BR are too ambiguous outside a report
+    if t.len() >= 3 && SiftParser::parse(Rule::present_weather, t).is_ok(){
+        return Some(SimplePattern::Weather);
+    }
+    if t.starts_with("RE") && SiftParser::parse(Rule::recent_weather, t).is_ok(){
+        return Some(SimplePattern::RecentWeather);
+    }
+    if t.starts_with('R') && t.contains('/') && SiftParser::parse(Rule::runway_state, t).is_ok(){
+        return Some(SimplePattern::RunwayState);
+    }
+    if t.starts_with('W') && SiftParser::parse(Rule::sea_state, t).is_ok(){
+        return Some(SimplePattern::SeaState);
+    }
     // allow diff variants containing VFR
     if t.contains("VFR") && t.chars().all(|c| c.is_ascii_alphabetic()) {
         return Some(SimplePattern::FlightCategory);
@@ -100,47 +161,353 @@ pub fn apply_pattern(prefix: &str,token: &str, pat: SimplePattern,out: &mut Hash
     }};
     match pat{
         SimplePattern::TempDew=>{
-            let p: Vec<&str> = token.split('/').collect(); //split into 2 hlfs
-            if p.len()==2{
-                out.insert(col("temp_c"),p[0].replace('M', "-")); //M == minus
-                out.insert(col("dewpoint_c"),p[1].replace('M', "-"));
+            if let Some((t, d)) = split_temp_dew(token){
+                for (name, v) in [("temp_c", t), ("dewpoint_c", d)]{
+                    // M or // alone == not measured
+                    if v == "M" || v == "//"{
+                        insert_missing(&col(name), out);
+                    } else{
+                        out.insert(col(name),v.replace('M', "-")); //M == minus
+                    }
+                }
             } else {
-                out.insert(col("tempdew_raw"),token.into()); //if more than 2 parts
+                out.insert(col("tempdew_raw"),token.into()); //not a temp/dew pair
         }
         } //same logic for next known patterns
         SimplePattern::Wind=>{
-            let core= &token[..token.len() - 2];// drop KT
+            let unit = WIND_UNITS.iter().find(|u| token.ends_with(*u)).copied().unwrap_or("KT");
+            let core= &token[..token.len() - unit.len()];// drop units
+            // /////KT == wind sensor gave nothing
+            if core.starts_with("///"){
+                insert_missing(&col("wind_direction"), out);
+                insert_missing(&col("wind_speed"), out);
+                out.insert(col("wind_units"), unit.into());
+                return;
+            }
             let (dir,rest) = core.split_at(3);
-            out.insert(col("wind_direction"), dir.into());
-            if let Some(g) =rest.find('G'){
-                out.insert(col("wind_speed"),rest[..g].into());
-                out.insert(col("wind_gust"), rest[g + 1..].into());
-            } else{
-            out.insert(col("wind_speed"), rest.into());
+            // VRB has no direction, only the flag
+            let variable = dir == "VRB";
+            out.insert(col("wind_direction"), if variable { String::new() } else { dir.into() });
+            out.insert(col("wind_variable"), variable.to_string());
+            let (speed, gust) = match rest.find('G'){
+                Some(g) => (&rest[..g], Some(&rest[g + 1..])),
+                None => (rest, None),
+            };
+            out.insert(col("wind_speed"), speed.into());
+            // same speeds in knots, so mps/kmh regions can be compared
+            if let Some(kt) = speed_to_kt(speed, unit){
+                out.insert(col("wind_speed_kt"), kt);
             }
-           out.insert(col("wind_units"), "KT".into());
+            if let Some(g) = gust{
+                out.insert(col("wind_gust"), g.into());
+                if let Some(kt) = speed_to_kt(g, unit){
+                    out.insert(col("wind_gust_kt"), kt);
+                }
+            }
+            // 00000KT == calm
+            let calm = dir == "000" && speed.parse::<u32>() == Ok(0);
+            out.insert(col("wind_calm"), calm.to_string());
+            out.insert(col("wind_units"), unit.into());
+        }
+        SimplePattern::WindVariation=>{
+            let (from, to) = token.split_at(3);
+            out.insert(col("wind_var_from_deg"), from.into());
+            out.insert(col("wind_var_to_deg"), to[1..].into());
         }
         SimplePattern::Pressure=>{
-            if let Ok(v) =token[1..].parse::<f32>(){
-                out.insert(col("pressure_inhg"), format!("{:.2}", v / 100.0));
+            // both units for every report, whatever the source form
+            if token[1..].starts_with("//"){
+                insert_missing(&col("pressure_hpa"), out);
+                insert_missing(&col("pressure_inhg"), out);
+            } else if let Ok(v) =token[1..].parse::<f64>(){
+                let (hpa, inhg, unit) = if token.starts_with('Q'){
+                    (v, v / HPA_PER_INHG, "hPa")
+                } else{
+                    (v / 100.0 * HPA_PER_INHG, v / 100.0, "inHg")
+                };
+                out.insert(col("pressure_hpa"), format!("{:.1}", hpa));
+                out.insert(col("pressure_inhg"), format!("{:.2}", inhg));
+                out.insert(col("pressure_unit"), unit.into());
             } else {
                 out.insert(col("pressure_raw"), token.into()); // into is same as to_str
         }}
-        SimplePattern::Time=>{out.insert(col("time"), token.into());}
+        SimplePattern::Time=>{
+            out.insert(col("time"), token.into());
+            // DDHHMMZ split, full timestamp is done by resolve_obs_time
+            for (name, range) in [("obs_day", 0..2), ("obs_hour", 2..4), ("obs_minute", 4..6)]{
+                if let Some(v) = token.get(range){
+                    out.insert(col(name), v.into());
+                }
+            }
+        }
         SimplePattern::Visibility=>{
             let v=token.trim_end_matches("SM").trim();
+            out.insert(col("visibility_unit"), "SM".into());
+            if v.starts_with('/'){
+                insert_missing(&col("visibility_sm"), out);
+                insert_missing(&col("visibility_m"), out);
+                return;
+            }
             out.insert(col("visibility_sm"), v.into());
+            // P6 == more than 6, M1/4 == less than 1/4
+            let (qualifier, num) = if let Some(n) = v.strip_prefix('P'){
+                ("above", n)
+            } else if let Some(n) = v.strip_prefix('M'){
+                ("below", n)
+            } else{
+                ("", v)
+            };
+            out.insert(col("visibility_qualifier"), qualifier.into());
+            if let Some(sm) = parse_fraction(num){
+                out.insert(col("visibility_m"), format!("{:.0}", sm * METRES_PER_SM));
+            }
+        }
+        SimplePattern::VisibilityMetric=>{
+            let (num, dir) = token.split_at(4.min(token.len()));
+            if num == "////"{
+                out.insert(col("visibility_unit"), "m".into());
+                insert_missing(&col("visibility_m"), out);
+                return;
+            }
+            out.insert(col("visibility_unit"), "m".into());
+            // 9999 == 10 km or more
+            if num == "9999"{
+                out.insert(col("visibility_m"), "10000".into());
+                out.insert(col("visibility_qualifier"), "above".into());
+            } else{
+                out.insert(col("visibility_m"), num.parse::<u32>().map_or(num.into(), |v| v.to_string()));
+                out.insert(col("visibility_qualifier"), String::new());
+            }
+            out.insert(col("visibility_ndv"), (dir == "NDV").to_string());
+            out.insert(col("visibility_dir"), if dir == "NDV" { String::new() } else { dir.into() });
+        }
+        SimplePattern::VisibilityMin=>{
+            // 1500SW == lowest visibility and where it is
+            let (num, dir) = token.split_at(4.min(token.len()));
+            out.insert(col("visibility_min_m"), num.parse::<u32>().map_or(num.into(), |v| v.to_string()));
+            out.insert(col("visibility_min_dir"), dir.into());
         }
 
+        SimplePattern::VerticalVisibility=>{
+            // VV002 == sky obscured, can see 200ft up
+            match token[2..].parse::<u32>(){
+                Ok(v) => {out.insert(col("vertical_visibility_ft"), (v * 100).to_string());}
+                Err(_) => insert_missing(&col("vertical_visibility_ft"), out),
+            }
+        }
+        SimplePattern::Cavok=>{
+            // expand into what cavok implies
+            out.insert(col("cavok"), "true".into());
+            out.insert(col("visibility_m"), "10000".into());
+            out.insert(col("visibility_unit"), "m".into());
+            out.insert(col("visibility_qualifier"), "above".into());
+            out.insert(col("sky_clear_below_ft"), "5000".into());
+            out.insert(col("wx_significant"), "false".into());
+        }
+        SimplePattern::Rvr=>{
+            let group = format!("rvr[{}]", next_index(out, &col("rvr")));
+            let gcol = |name: &str| col(&format!("{group}.{name}"));
+            let Some((runway, mut rest)) = token[1..].split_once('/') else{
+                out.insert(gcol("raw"), token.into());
+                return;
+            };
+            out.insert(gcol("runway"), runway.into());
+            // tendency is the last letter (FT ends with T so no clash)
+            let tendency = match rest.chars().last(){
+                Some('U') => "up",
+                Some('D') => "down",
+                Some('N') => "no change",
+                _ => "",
+            };
+            if !tendency.is_empty(){
+                rest = rest[..rest.len() - 1].trim_end_matches('/');
+            }
+            out.insert(gcol("tendency"), tendency.into());
+            let unit = if let Some(r) = rest.strip_suffix("FT"){
+                rest = r;
+                "FT"
+            } else{
+                "M"
+            };
+            out.insert(gcol("unit"), unit.into());
+            // min V max, single value == min and max are same
+            let (min, max) = rest.split_once('V').unwrap_or((rest, rest));
+            for (name, v) in [("min", min), ("max", max)]{
+                let (qualifier, num) = match v.chars().next(){
+                    Some('P') => ("above", &v[1..]),
+                    Some('M') => ("below", &v[1..]),
+                    _ => ("", v),
+                };
+                out.insert(gcol(name), num.parse::<u32>().map_or(num.into(), |n| n.to_string()));
+                out.insert(gcol(&format!("{name}_qualifier")), qualifier.into());
+            }
+        }
         SimplePattern::Cloud=>{
-            let code = if token.len() >= 3 { &token[..3] } else { token };
-            let cover_str = match code {"BKN" => "broken","SCT" => "scattered","FEW" => "few","OVC" => "overcast","CLR" => "clear","SKC" => "clear",_ => code,};
-            out.insert("cloud_cover".into(), cover_str.into());
-            if token.len()>3{
-                if let Ok(v) = token[3..].parse::<u32>(){
-                    out.insert("cloud_altitude_ft".into(),(v * 100).to_string());
+            // every layer gets own index: sky[0], sky[1], ...
+            let layer = format!("sky[{}]", next_index(out, &col("sky")));
+            let lcol = |name: &str| col(&format!("{layer}.{name}"));
+            let code = token.get(..3).unwrap_or(token);
+            let cover_str = match code {"BKN" => "broken","SCT" => "scattered","FEW" => "few","OVC" => "overcast","CLR" => "clear","SKC" => "clear",
+                "NSC" => "no significant cloud","NCD" => "no cloud detected",_ => code,};
+            if code == "///"{
+                insert_missing(&lcol("cover"), out); // auto station could not tell the amount
+            } else{
+                out.insert(lcol("cover"), cover_str.into());
+            }
+            // CLR == nothing below 12000ft (auto sensor limit), NSC == nothing below 5000ft
+            match code{
+                "CLR" => {out.insert(col("sky_clear_below_ft"), "12000".into());}
+                "NSC" => {out.insert(col("sky_clear_below_ft"), "5000".into());}
+                _ => {}
+            }
+            if let Some(alt) = token.get(3..6){
+                if let Ok(v) = alt.parse::<u32>(){
+                    out.insert(lcol("base_ft"),(v * 100).to_string());
+                } else if alt == "///"{
+                    insert_missing(&lcol("base_ft"), out); // height not measured
                 } else {
-                    out.insert("cloud_raw".into(),token.into());
+                    out.insert(lcol("raw"),token.into());
+                }
+            }
+            // convective cloud suffix
+            match token.get(6..){
+                Some("CB") => {out.insert(lcol("type"), "cumulonimbus".into());}
+                Some("TCU") => {out.insert(lcol("type"), "towering cumulus".into());}
+                Some("///") => insert_missing(&lcol("type"), out),
+                _ => {}
+            }
+        }
+        SimplePattern::Weather | SimplePattern::RecentWeather=>{
+            // RERA == rain that ended before the observation, same parts as present weather
+            let (base, code) = match pat{
+                SimplePattern::RecentWeather => ("recent_wx", &token[2..]),
+                _ => ("wx", token),
+            };
+            let group = format!("{base}[{}]", next_index(out, &col(base)));
+            let gcol = |name: &str| col(&format!("{group}.{name}"));
+            if let Some(mut wx) = split_weather(code){
+                // RE groups carry no intensity, do not guess moderate
+                if matches!(pat, SimplePattern::RecentWeather){
+                    wx.intensity = "";
+                }
+                out.insert(gcol("code"), token.into());
+                out.insert(gcol("intensity"), wx.intensity.into());
+                out.insert(gcol("proximity"), wx.proximity.into());
+                out.insert(gcol("descriptor"), wx.descriptor.into());
+                out.insert(gcol("precipitation"), wx.precipitation.join(";"));
+                out.insert(gcol("obscuration"), wx.obscuration.join(";"));
+                out.insert(gcol("other"), wx.other.join(";"));
+                out.insert(gcol("description"), wx.description());
+            } else {
+                out.insert(gcol("raw"), token.into());
+            }
+        }
+        SimplePattern::WindShear=>{
+            // WS R27 / WS RWY27L / WS TKOF RWY20 / WS ALL RWY
+            let group = format!("wind_shear[{}]", next_index(out, &col("wind_shear")));
+            let gcol = |name: &str| col(&format!("{group}.{name}"));
+            let mut phase = "";
+            let mut runway = "";
+            for part in token.split(' ').skip(1){
+                match part{
+                    "TKOF" => phase = "takeoff",
+                    "LDG" => phase = "landing",
+                    "ALL" => runway = "ALL",
+                    "RWY" => {}
+                    p => runway = p.trim_start_matches("RWY").trim_start_matches('R'),
+                }
+            }
+            out.insert(gcol("runway"), runway.into());
+            out.insert(gcol("phase"), phase.into());
+        }
+        SimplePattern::RunwayState=>{
+            let group = format!("runway_state[{}]", next_index(out, &col("runway_state")));
+            let gcol = |name: &str| col(&format!("{group}.{name}"));
+            let Some((runway, state)) = token[1..].split_once('/') else{
+                out.insert(gcol("raw"), token.into());
+                return;
+            };
+            // 88 == all runways, 99 == repeat of last report
+            let runway = match runway{ "88" => "all", "99" => "repeated", r => r };
+            out.insert(gcol("runway"), runway.into());
+            if state == "SNOCLO"{
+                out.insert(gcol("snow_closed"), "true".into());
+                return;
+            }
+            let (cleared, braking) = match state.strip_prefix("CLRD"){
+                Some(b) => (true, b),
+                None => (false, state.get(4..).unwrap_or("")),
+            };
+            out.insert(gcol("cleared"), cleared.to_string());
+            if !cleared{
+                let part = |r: std::ops::Range<usize>| state.get(r).unwrap_or("/");
+                match lookup(RUNWAY_DEPOSIT, part(0..1)){
+                    Some(d) => {out.insert(gcol("deposit"), d.into());}
+                    None => insert_missing(&gcol("deposit"), out),
+                }
+                match lookup(RUNWAY_EXTENT, part(1..2)){
+                    Some(e) => {out.insert(gcol("extent"), e.into());}
+                    None => insert_missing(&gcol("extent"), out),
+                }
+                // 00-90 mm, 92-98 == 10..40 cm, 99 == runway not operational
+                match part(2..4).parse::<u32>(){
+                    Ok(99) => {
+                        out.insert(gcol("depth_mm"), String::new());
+                        out.insert(gcol("not_operational"), "true".into());
+                    }
+                    Ok(d) if d >= 92 => {out.insert(gcol("depth_mm"), ((d - 90) * 50).to_string());}
+                    Ok(d) => {out.insert(gcol("depth_mm"), d.to_string());}
+                    Err(_) => insert_missing(&gcol("depth_mm"), out),
+                }
+            }
+            // 01-90 friction coefficient, 91-95 estimated braking action, 99 unreliable
+            match braking.parse::<u32>(){
+                Ok(b) if b <= 90 => {out.insert(gcol("braking_coefficient"), format!("0.{:02}", b));}
+                Ok(b) => {
+                    let action = match b{ 91 => "poor", 92 => "medium/poor", 93 => "medium", 94 => "medium/good", 95 => "good", _ => "unreliable" };
+                    out.insert(gcol("braking_action"), action.into());
+                }
+                Err(_) => insert_missing(&gcol("braking_action"), out),
+            }
+        }
+        SimplePattern::SeaState=>{
+            let Some((temp, surface)) = token[1..].split_once('/') else{
+                out.insert(col("sea_raw"), token.into());
+                return;
+            };
+            if temp == "//"{
+                insert_missing(&col("sea_surface_temp_c"), out);
+            } else{
+                out.insert(col("sea_surface_temp_c"), temp.replace('M', "-"));
+            }
+            let (kind, v) = surface.split_at(1);
+            if kind == "S"{
+                match lookup(SEA_STATE, v){
+                    Some(desc) => {
+                        out.insert(col("sea_state_code"), v.into());
+                        out.insert(col("sea_state"), desc.into());
+                    }
+                    None => insert_missing(&col("sea_state"), out),
+                }
+            } else{
+                // wave height in decimetres
+                match v.parse::<f64>(){
+                    Ok(dm) => {out.insert(col("wave_height_m"), format!("{:.1}", dm / 10.0));}
+                    Err(_) => insert_missing(&col("wave_height_m"), out),
+                }
+            }
+        }
+        SimplePattern::ColourState=>{
+            let closed = token.starts_with("BLACK");
+            let colour = token.trim_start_matches("BLACK");
+            // first code is current state, second one is forecast
+            let name = if out.contains_key(&col("colour_state")) { "colour_state_forecast" } else { "colour_state" };
+            out.insert(col(name), colour.into());
+            if name == "colour_state"{
+                out.insert(col("airfield_closed"), closed.to_string());
+                if let Some((_, vis, base)) = COLOUR_STATES.iter().find(|(c, _, _)| *c == colour){
+                    out.insert(col("colour_min_visibility_m"), vis.to_string());
+                    out.insert(col("colour_min_base_ft"), base.to_string());
                 }
             }
         }
@@ -150,45 +517,520 @@ pub fn apply_pattern(prefix: &str,token: &str, pat: SimplePattern,out: &mut Hash
     }}
 }
 
-fn visit_metar(pair: &pest::iterators::Pair<Rule>, out: &mut HashMap<String, String>){
+//prefix is "" for observed groups and trend[n] inside trend sections
+pub(crate) fn visit_metar(pair: &pest::iterators::Pair<Rule>, prefix: &str, out: &mut HashMap<String, String>){
     let text = norm(pair.as_str()); // normalize raw text from this node
+    let col = |name: &str| if prefix.is_empty(){ name.to_string() } else { format!("{prefix}.{name}") };
     match pair.as_rule() {
+        // flags default to false, modifiers below switch them on
+        Rule::metar_report => {
+        for flag in ["is_correction", "is_automated", "is_amended"]{
+            out.insert(flag.into(), "false".into());
+        }
+        }
+        Rule::report_type => {
+        out.insert("report_type".into(), text.clone());
+        }
+        Rule::modifier => {
+        let flag = match text.as_str(){
+            "COR" => "is_correction",
+            "AUTO" => "is_automated",
+            _ => "is_amended",
+        };
+        out.insert(flag.into(), "true".into());
+        }
         // station already validated by grammar
         Rule::station => {
         out.insert("station".into(), text.clone());
         }
         //time already validated by grammar
         Rule::time =>{
-        apply_pattern("", &text, SimplePattern::Time, out);
+        apply_pattern(prefix, &text, SimplePattern::Time, out);
         }
         //wind: use same logic as for tokens
         Rule::wind => {
-        apply_pattern("", &text, SimplePattern::Wind, out);
+        apply_pattern(prefix, &text, SimplePattern::Wind, out);
+        }
+        Rule::wind_variation=>{
+        apply_pattern(prefix, &text, SimplePattern::WindVariation, out);
         }
         Rule::visibility=>{
-        apply_pattern("", &text, SimplePattern::Visibility, out);
+        apply_pattern(prefix, &text, SimplePattern::Visibility, out);
+        }
+        Rule::visibility_metric=>{
+        apply_pattern(prefix, &text, SimplePattern::VisibilityMetric, out);
+        }
+        Rule::visibility_min=>{
+        apply_pattern(prefix, &text, SimplePattern::VisibilityMin, out);
+        }
+        Rule::cavok=>{
+        apply_pattern(prefix, &text, SimplePattern::Cavok, out);
+        }
+        Rule::rvr=>{
+        apply_pattern(prefix, &text, SimplePattern::Rvr, out);
+        }
+        Rule::present_weather=>{
+        apply_pattern(prefix, &text, SimplePattern::Weather, out);
+        }
+        Rule::recent_weather=>{
+        apply_pattern(prefix, &text, SimplePattern::RecentWeather, out);
+        }
+        Rule::wind_shear=>{
+        apply_pattern(prefix, &text, SimplePattern::WindShear, out);
+        }
+        Rule::vertical_visibility=>{
+        apply_pattern(prefix, &text, SimplePattern::VerticalVisibility, out);
+        }
+        Rule::runway_state=>{
+        apply_pattern(prefix, &text, SimplePattern::RunwayState, out);
+        }
+        Rule::sea_state=>{
+        apply_pattern(prefix, &text, SimplePattern::SeaState, out);
+        }
+        Rule::colour_state=>{
+        apply_pattern(prefix, &text, SimplePattern::ColourState, out);
         }
         Rule::clouds =>{
-        apply_pattern("", &text, SimplePattern::Cloud, out);
+        apply_pattern(prefix, &text, SimplePattern::Cloud, out);
         }
         Rule::temp_dew=>{
-        apply_pattern("", &text, SimplePattern::TempDew, out);
+        apply_pattern(prefix, &text, SimplePattern::TempDew, out);
         }
         Rule::pressure=> {
-        apply_pattern("", &text, SimplePattern::Pressure, out);
+        apply_pattern(prefix, &text, SimplePattern::Pressure, out);
+        }
+        // each BECMG/TEMPO section gets own trend[n], so forecast never overwrites observed values
+        Rule::trend_change=>{
+        let trend = format!("trend[{}]", next_index(out, "trend"));
+        for inner in pair.clone().into_inner(){
+            visit_metar(&inner, &trend, out);
+        }
+        return;
+        }
+        Rule::trend_nosig=>{
+        let trend = format!("trend[{}]", next_index(out, "trend"));
+        out.insert(format!("{trend}.change"), "NOSIG".into());
+        }
+        Rule::trend_type=>{
+        out.insert(col("change"), text.clone());
+        }
+        // FM1100 / TL1200 / AT1130
+        Rule::trend_time=>{
+        let name = match &text[..2]{ "FM" => "from", "TL" => "until", _ => "at" };
+        out.insert(col(name), text[2..].into());
+        }
+        Rule::nsw=>{
+        out.insert(col("wx_significant"), "false".into());
+        }
+        // remarks are decoded under rmk.*
+        Rule::remarks=>{
+        out.insert("rmk.maintenance".into(), "false".into());
+        // sensors are ok unless remarks say otherwise, so "no thunder" != "no thunder sensor"
+        for (_, name) in SENSOR_STATUS{
+            out.insert(format!("rmk.{name}"), "false".into());
+        }
+        for inner in pair.clone().into_inner(){
+            visit_metar(&inner, "rmk", out);
+        }
+        return;
+        }
+        Rule::rmk_station_type=>{
+        out.insert(col("station_type"), text.clone());
+        let desc = if text.starts_with("AO1"){
+            "automated without precipitation discriminator"
+        } else{
+            "automated with precipitation discriminator"
+        };
+        out.insert(col("station_type_desc"), desc.into());
+        }
+        // SLP982 == 998.2, SLP123 == 1012.3
+        Rule::rmk_slp=>{
+        if let Ok(v) = text[3..].parse::<f64>(){
+            let hpa = if v < 500.0 { 1000.0 + v / 10.0 } else { 900.0 + v / 10.0 };
+            out.insert(col("slp_hpa"), format!("{:.1}", hpa));
+        }
+        }
+        Rule::rmk_hourly_temp=>{
+        if let Some(t) = tenths(&text[1..5]){
+            out.insert(col("temp_c"), t);
+        }
+        if let Some(d) = text.get(5..9).and_then(tenths){
+            out.insert(col("dewpoint_c"), d);
+        }
+        }
+        Rule::rmk_precip_1h | Rule::rmk_precip_3_6h | Rule::rmk_precip_24h=>{
+        let name = match pair.as_rule(){
+            Rule::rmk_precip_1h => "precip_1h",
+            Rule::rmk_precip_3_6h => "precip_3_6h",
+            _ => "precip_24h",
+        };
+        insert_precip(&col(name), &text[1..], out);
+        // 6RRRR is 3h amount at 03/09/15/21Z (+-1h) and 6h amount at synoptic hours
+        if pair.as_rule() == Rule::rmk_precip_3_6h
+            && let Some(h) = out.get("obs_hour").and_then(|h| h.parse::<u32>().ok()){
+            let period = if matches!(h % 6, 2..=4) { "3" } else { "6" };
+            out.insert(col("precip_3_6h_period_h"), period.into());
+        }
+        }
+        Rule::rmk_max_temp_6h | Rule::rmk_min_temp_6h=>{
+        let name = if pair.as_rule() == Rule::rmk_max_temp_6h { "max_temp_6h_c" } else { "min_temp_6h_c" };
+        if let Some(t) = tenths(&text[1..5]){
+            out.insert(col(name), t);
+        }
+        }
+        Rule::rmk_temp_24h=>{
+        if let Some(t) = tenths(&text[1..5]){
+            out.insert(col("max_temp_24h_c"), t);
+        }
+        if let Some(t) = tenths(&text[5..9]){
+            out.insert(col("min_temp_24h_c"), t);
+        }
+        }
+        Rule::rmk_snow_depth=>{
+        if let Ok(v) = text[2..].parse::<u32>(){
+            out.insert(col("snow_depth_in"), v.to_string());
+        }
+        }
+        Rule::rmk_peak_wind=>{
+        // "PK WND 28045/1955"
+        let group = &text[7..];
+        if let Some((wind, time)) = group.split_once('/'){
+            out.insert(col("peak_wind_dir"), wind[..3].into());
+            out.insert(col("peak_wind_speed_kt"), wind[3..].into());
+            out.insert(col("peak_wind_time"), rmk_hhmm(time, out));
+        }
+        }
+        Rule::rmk_wind_shift=>{
+        // "WSHFT 1715 FROPA"
+        let mut parts = text.split(' ').skip(1);
+        if let Some(time) = parts.next(){
+            out.insert(col("wind_shift_time"), rmk_hhmm(time, out));
+        }
+        out.insert(col("frontal_passage"), (parts.next() == Some("FROPA")).to_string());
+        }
+        Rule::rmk_pressure_tendency=>{
+        let code = &text[1..2];
+        // 0-3 rising, 4 steady, 5-8 falling
+        let sign = match code{ "0" | "1" | "2" | "3" => 1.0, "4" => 0.0, _ => -1.0 };
+        out.insert(col("pressure_tendency_code"), code.into());
+        if let Some(desc) = lookup(PRESSURE_TENDENCY, code){
+            out.insert(col("pressure_tendency_desc"), desc.into());
+        }
+        if let Ok(v) = text[2..].parse::<f64>(){
+            out.insert(col("pressure_change_3h_hpa"), format!("{:.1}", sign * v / 10.0));
+        }
+        }
+        Rule::rmk_pressure_rapid=>{
+        let name = if text == "PRESRR" { "pressure_rising_rapidly" } else { "pressure_falling_rapidly" };
+        out.insert(col(name), "true".into());
+        }
+        Rule::rmk_sensor_status=>{
+        let (code, location) = text.split_once(' ').unwrap_or((&text, ""));
+        if let Some(name) = lookup(SENSOR_STATUS, code){
+            out.insert(col(name), "true".into());
+            if !location.is_empty(){
+                out.insert(col(&format!("{name}_location")), location.into());
+            }
+        }
+        }
+        Rule::rmk_lightning=>{
+        let mut types = Vec::new();
+        let mut dirs = Vec::new();
+        for inner in pair.clone().into_inner(){
+            let v = inner.as_str();
+            match inner.as_rule(){
+                Rule::rmk_ltg_freq => {
+                    let freq = match v{ "OCNL" => "occasional", "FRQ" => "frequent", _ => "continuous" };
+                    out.insert(col("lightning_frequency"), freq.into());
+                }
+                Rule::rmk_ltg_type => types.push(v),
+                Rule::rmk_ltg_dist => {
+                    let dist = match v{ "DSNT" => "distant", "VC" => "vicinity", _ => "overhead" };
+                    out.insert(col("lightning_distance"), dist.into());
+                }
+                Rule::rmk_ltg_dir => dirs.push(v),
+                _ => {}
+            }
+        }
+        out.insert(col("lightning"), "true".into());
+        out.insert(col("lightning_types"), types.join(";"));
+        out.insert(col("lightning_direction"), dirs.join(";"));
+        return;
+        }
+        // every phenomenon gets own wx_event[n] with begin/end times
+        Rule::rmk_begin_end=>{
+        let mut event = String::new();
+        for inner in pair.clone().into_inner(){
+            let v = inner.as_str();
+            match inner.as_rule(){
+                Rule::rmk_be_phenomenon => {
+                    event = col(&format!("wx_event[{}]", next_index(out, &col("wx_event"))));
+                    out.insert(format!("{event}.code"), v.into());
+                    if let Some(wx) = split_weather(v){
+                        out.insert(format!("{event}.description"), wx.description());
+                    }
+                }
+                Rule::rmk_be_time => {
+                    let name = if v.starts_with('B') { "begin" } else { "end" };
+                    let time = rmk_hhmm(&v[1..], out);
+                    let cell = out.entry(format!("{event}.{name}")).or_default();
+                    if !cell.is_empty(){ cell.push(';'); }
+                    cell.push_str(&time);
+                }
+                _ => {}
+            }
+        }
+        return;
+        }
+        Rule::rmk_maintenance=>{
+        out.insert(col("maintenance"), "true".into());
+        }
+        // groups we could not place are kept, not dropped
+        Rule::unparsed_group | Rule::trend_unparsed | Rule::rmk_unparsed=>{
+        let col = out.entry(col("unparsed")).or_default();
+        if !col.is_empty(){ col.push(' '); }
+        col.push_str(&text);
         }
         _ => {}//ignore else
     }
     //recursion for into_inner== children of this node in pest parse tree
     for inner in pair.clone().into_inner(){
-        visit_metar(&inner, out);
+        visit_metar(&inner, prefix, out);
 }
 }
 
+//sign digit + 3 digits in tenths: "1017" == -1.7, "0172" == 17.2
+fn tenths(s: &str)->Option<String>{
+    let (sign, num) = s.split_at_checked(1)?;
+    let v = num.parse::<f64>().ok()? / 10.0;
+    match sign{
+        "0" => Some(format!("{:.1}", v)),
+        "1" => Some(format!("{:.1}", -v)),
+        _ => None,
+    }
+}
+
+//precip amount in hundredths of inch => name_in, 0000 == trace, //// == not measured (empty)
+fn insert_precip(name: &str, amount: &str, out: &mut HashMap<String, String>){
+    match amount.parse::<f64>(){
+        Ok(v) => {
+            out.insert(format!("{name}_in"), format!("{:.2}", v / 100.0));
+            out.insert(format!("{name}_trace"), (v == 0.0).to_string());
+        }
+        Err(_) => {
+            out.insert(format!("{name}_in"), String::new());
+        }
+    }
+}
+
+//remark time as hhmm, only minutes == hour of observation
+fn rmk_hhmm(time: &str, out: &HashMap<String, String>)->String{
+    match (time.len(), out.get("obs_hour")){
+        (2, Some(h)) => format!("{h}{time}"),
+        _ => time.into(),
+    }
+}
+
+//runway state deposit (E) and extent (C) digits, wmo/icao motne tables
+const RUNWAY_DEPOSIT: &[(&str, &str)] = &[("0","clear and dry"),("1","damp"),("2","wet or water patches"),
+    ("3","rime or frost"),("4","dry snow"),("5","wet snow"),("6","slush"),("7","ice"),("8","compacted snow"),("9","frozen ruts")];
+const RUNWAY_EXTENT: &[(&str, &str)] = &[("1","10% or less"),("2","11-25%"),("5","26-50%"),("9","51-100%")];
+
+//wmo code table 3700, state of the sea
+const SEA_STATE: &[(&str, &str)] = &[("0","calm (glassy)"),("1","calm (rippled)"),("2","smooth"),("3","slight"),
+    ("4","moderate"),("5","rough"),("6","very rough"),("7","high"),("8","very high"),("9","phenomenal")];
+
+//military colour states: code, min visibility (m), min base of lowest 3/8 (SCT) or more cloud (ft)
+//state is the best one where both visibility and cloud base are at or above the minimum
+//YLO (no number) is the older single yellow, same as YLO2; RED is anything below AMB
+const COLOUR_STATES: [(&str, u32, u32); 8] = [
+    ("BLU", 8000, 2500),
+    ("WHT", 5000, 1500),
+    ("GRN", 3700, 700),
+    ("YLO1", 2500, 500),
+    ("YLO2", 1600, 300),
+    ("YLO", 1600, 300),
+    ("AMB", 800, 200),
+    ("RED", 0, 0),
+];
+
+//sensor outage remarks => columns
+const SENSOR_STATUS: &[(&str, &str)] = &[
+    ("RVRNO", "sensor_rvr_offline"),
+    ("PWINO", "sensor_pwi_offline"),
+    ("PNO", "sensor_precip_offline"),
+    ("FZRANO", "sensor_fzra_offline"),
+    ("TSNO", "sensor_ts_offline"),
+    ("VISNO", "sensor_vis_offline"),
+    ("CHINO", "sensor_chi_offline"),
+];
+
+//wmo code table 0200, characteristic of pressure tendency
+const PRESSURE_TENDENCY: &[(&str, &str)] = &[
+    ("0", "increasing, then decreasing"),
+    ("1", "increasing, then steady"),
+    ("2", "increasing"),
+    ("3", "decreasing or steady, then increasing"),
+    ("4", "steady"),
+    ("5", "decreasing, then increasing"),
+    ("6", "decreasing, then steady"),
+    ("7", "decreasing"),
+    ("8", "steady or increasing, then decreasing"),
+];
+
+//wind speed units as they appear in reports
+const WIND_UNITS: [&str; 3] = ["KT", "MPS", "KMH"];
+
+//speed in given unit => knots, rounded
+fn speed_to_kt(v: &str, unit: &str)->Option<String>{
+    let v = v.parse::<f64>().ok()?;
+    let kt = match unit{
+        "MPS" => v * 1.943_844,
+        "KMH" => v / 1.852,
+        _ => v,
+    };
+    Some(format!("{:.0}", kt))
+}
+
+pub(crate) const METRES_PER_SM: f64 = 1609.344;
+const HPA_PER_INHG: f64 = 33.8639;
+
+//"1 1/2" / "3/4" / "10" => number
+fn parse_fraction(s: &str)->Option<f64>{
+    let mut total = 0.0;
+    for part in s.split_whitespace(){
+        total += match part.split_once('/'){
+            Some((n, d)) => n.parse::<f64>().ok()? / d.parse::<f64>().ok().filter(|d| *d != 0.0)?,
+            None => part.parse::<f64>().ok()?,
+        };
+    }
+    Some(total)
+}
+
+//weather codes => words
+const WX_DESCRIPTORS: &[(&str, &str)] = &[("MI","shallow"),("PR","partial"),("BC","patches"),("DR","low drifting"),
+    ("BL","blowing"),("SH","showers"),("TS","thunderstorm"),("FZ","freezing")];
+const WX_PRECIPITATION: &[(&str, &str)] = &[("DZ","drizzle"),("RA","rain"),("SN","snow"),("SG","snow grains"),
+    ("IC","ice crystals"),("PL","ice pellets"),("GR","hail"),("GS","small hail"),("UP","unknown precipitation")];
+const WX_OBSCURATION: &[(&str, &str)] = &[("BR","mist"),("FG","fog"),("FU","smoke"),("VA","volcanic ash"),
+    ("DU","widespread dust"),("SA","sand"),("HZ","haze"),("PY","spray")];
+const WX_OTHER: &[(&str, &str)] = &[("PO","dust whirls"),("SQ","squalls"),("FC","funnel cloud"),
+    ("SS","sandstorm"),("DS","duststorm")];
+
+//one present weather group split into parts (words, not codes)
+#[derive(Default)]
+struct WeatherGroup{
+    intensity: &'static str,
+    proximity: &'static str,
+    descriptor: &'static str,
+    precipitation: Vec<&'static str>,
+    obscuration: Vec<&'static str>,
+    other: Vec<&'static str>,
+}
+
+impl WeatherGroup{
+    //human readable text like "heavy thunderstorm with rain"
+    fn description(&self)->String{
+        let phen: Vec<&str> = self.precipitation.iter().chain(&self.obscuration).chain(&self.other).copied().collect();
+        let mut words: Vec<String> = Vec::new();
+        if self.intensity == "light" || self.intensity == "heavy"{
+            words.push(self.intensity.into());
+        }
+        match self.descriptor{
+            "" => words.push(phen.join(" and ")),
+            "showers" => {
+                if !phen.is_empty(){ words.push(phen.join(" and ")); }
+                words.push("showers".into());
+            }
+            "thunderstorm" => {
+                words.push("thunderstorm".into());
+                if !phen.is_empty(){ words.push(format!("with {}", phen.join(" and "))); }
+            }
+            d => {
+                words.push(d.into());
+                if !phen.is_empty(){ words.push(phen.join(" and ")); }
+            }
+        }
+        if !self.proximity.is_empty(){
+            words.push("in the vicinity".into());
+        }
+        words.join(" ")
+    }
+}
+
+pub(crate) fn lookup(table: &[(&str, &'static str)], code: &str)->Option<&'static str>{
+    table.iter().find(|(c, _)| *c == code).map(|(_, w)| *w)
+}
+
+//-RA, +TSRA, VCSH, FZFG ... => WeatherGroup, None if some part is unknown
+fn split_weather(code: &str)->Option<WeatherGroup>{
+    let mut wx = WeatherGroup::default();
+    let mut rest = code;
+    if let Some(r) = rest.strip_prefix('-'){
+        wx.intensity = "light";
+        rest = r;
+    } else if let Some(r) = rest.strip_prefix('+'){
+        wx.intensity = "heavy";
+        rest = r;
+    } else if let Some(r) = rest.strip_prefix("VC"){
+        wx.proximity = "vicinity";
+        rest = r;
+    }
+    if let Some(d) = rest.get(..2).and_then(|c| lookup(WX_DESCRIPTORS, c)){
+        wx.descriptor = d;
+        rest = &rest[2..];
+    }
+    while !rest.is_empty(){
+        let c = rest.get(..2)?;
+        if let Some(w) = lookup(WX_PRECIPITATION, c){
+            wx.precipitation.push(w);
+        } else if let Some(w) = lookup(WX_OBSCURATION, c){
+            wx.obscuration.push(w);
+        } else if let Some(w) = lookup(WX_OTHER, c){
+            wx.other.push(w);
+        } else{
+            return None;
+        }
+        rest = &rest[2..];
+    }
+    // no sign == moderate, but only for precipitation
+    if wx.intensity.is_empty() && wx.proximity.is_empty() && !wx.precipitation.is_empty(){
+        wx.intensity = "moderate";
+    }
+    Some(wx)
+}
+
 //utils
+//12/08, M02/M05, 12///, ///// => (temp, dew)
+//split by position, because // for missing value has slashes too
+fn split_temp_dew(token: &str) -> Option<(&str, &str)>{
+    let half = |v: &str| v == "M" || v == "//" || {
+        let d = v.strip_prefix('M').unwrap_or(v);
+        d.len() == 2 && d.bytes().all(|b| b.is_ascii_digit())
+    };
+    let t_len = if token.starts_with("//") { 2 } else if token.starts_with('M') && !token[1..].starts_with('/') { 3 } else if token.starts_with('M') { 1 } else { 2 };
+    let (t, rest) = (token.get(..t_len)?, token.get(t_len..)?);
+    let d = rest.strip_prefix('/')?;
+    (half(t) && half(d)).then_some((t, d))
+}
+
+//not reported value == empty cell + name_missing flag
+pub(crate) fn insert_missing(name: &str, out: &mut HashMap<String, String>){
+    out.insert(name.to_string(), String::new());
+    out.insert(format!("{name}_missing"), "true".into());
+}
+
+//first free index for base[n].* columns
+pub(crate) fn next_index(out: &HashMap<String, String>, base: &str) -> usize{
+    (0..).find(|i| {
+        let p = format!("{base}[{i}].");
+        !out.keys().any(|k| k.starts_with(&p))
+    }).unwrap_or(0)
+}
+
 #[inline]
 //normalize
-fn norm(s:&str)->String{
+pub(crate) fn norm(s:&str)->String{
     let mut t=s.trim().trim_end_matches(['=','+']).trim().to_string();
     t=t.replace(char::is_whitespace," ");
     while t.contains("  "){ t=t.replace("  "," ");}
@@ -0,0 +1,247 @@
+use pest::Parser;
+use std::collections::HashMap;
+use crate::date::UtcDate;
+use crate::metar::{Rule, SiftParser, SimplePattern, apply_pattern, insert_missing, lookup, next_index, norm};
+
+// parse full pilot report via pest into flat map
+//slash-field pirep (UA /OV ...) or positional airep (ARP ...), both give same columns
+pub fn decode_pirep(s: &str) -> Option<HashMap<String, String>>{
+    let s = norm(s);
+    decode_slash_fields(&s).or_else(|| decode_airep(&s))
+}
+
+//each /XX field is decoded into own columns, unknown parts go to unparsed
+fn decode_slash_fields(s: &str) -> Option<HashMap<String, String>>{
+    let report = SiftParser::parse(Rule::pirep_report, s).ok()?.next()?;
+    let mut out = HashMap::new();
+    for pair in report.into_inner(){
+        let text = pair.as_str().trim();
+        match pair.as_rule(){
+            Rule::pirep_station => {out.insert("station".into(), text.into());}
+            Rule::pirep_type => {
+                out.insert("report_type".into(), text.into());
+                out.insert("is_urgent".into(), (text == "UUA").to_string());
+            }
+            Rule::pirep_location | Rule::pirep_time => {
+                let tag = if pair.as_rule() == Rule::pirep_location { "OV" } else { "TM" };
+                let value = pair.into_inner().next().map_or("", |p| p.as_str().trim());
+                visit_field(tag, value, &mut out);
+            }
+            Rule::pirep_field => {
+                let mut inner = pair.into_inner();
+                let (Some(tag), Some(value)) = (inner.next(), inner.next()) else{
+                    continue;
+                };
+                visit_field(tag.as_str(), value.as_str().trim(), &mut out);
+            }
+            Rule::pirep_remarks => {
+                let rmk = pair.into_inner().next().map_or("", |p| p.as_str().trim());
+                out.insert("remarks".into(), rmk.into());
+            }
+            _ => {}
+        }
+    }
+    Some(out)
+}
+
+//ARP UAL902 4400N 16000E 0141 F380 MS51 257/063 TURB MOD
+fn decode_airep(s: &str) -> Option<HashMap<String, String>>{
+    let report = SiftParser::parse(Rule::airep_report, s).ok()?.next()?;
+    let mut out = HashMap::new();
+    for pair in report.into_inner(){
+        let text = pair.as_str().trim();
+        match pair.as_rule(){
+            Rule::airep_type => {
+                out.insert("report_type".into(), text.into());
+                out.insert("is_urgent".into(), (text == "ARS").to_string());
+            }
+            Rule::airep_callsign => {out.insert("aircraft_id".into(), text.into());}
+            Rule::airep_position => {
+                out.insert("location".into(), text.into());
+                for part in pair.into_inner(){
+                    let (name, width) = match part.as_rule(){
+                        Rule::airep_lat => ("latitude", 2),
+                        Rule::airep_lon => ("longitude", 3),
+                        _ => continue,
+                    };
+                    out.insert(name.into(), coordinate(part.as_str(), width));
+                }
+            }
+            // same columns as /TM and /FL
+            Rule::airep_time => visit_field("TM", text, &mut out),
+            Rule::airep_level => visit_field("FL", text.trim_start_matches(['F', 'L']), &mut out),
+            Rule::airep_temp => {
+                let sign = if text.starts_with("MS") { -1 } else { 1 };
+                if let Ok(t) = text[2..].parse::<i32>(){
+                    out.insert("temp_c".into(), (sign * t).to_string());
+                }
+            }
+            // 257/063 == 257 deg 63 kt
+            Rule::airep_wind => {
+                if let Some((dir, speed)) = text.trim_end_matches("KT").split_once('/'){
+                    visit_field("WV", &format!("{dir}{speed}KT"), &mut out);
+                }
+            }
+            Rule::airep_turbulence | Rule::airep_icing => {
+                let (name, types) = if pair.as_rule() == Rule::airep_turbulence { ("turbulence", TURBULENCE_TYPES) } else { ("icing", ICING_TYPES) };
+                let words = text.split_once(' ').map_or("", |(_, w)| w);
+                hazard(name, words, types, &mut out);
+            }
+            Rule::airep_unparsed => push_unparsed(text, &mut out),
+            _ => {}
+        }
+    }
+    Some(out)
+}
+
+//4430N => 44.5, 16000E => 160, S/W are negative
+fn coordinate(text: &str, width: usize) -> String{
+    let (digits, hemi) = text.split_at(text.len() - 1);
+    let deg = digits[..width].parse::<f64>().unwrap_or(0.0);
+    let min = digits[width..].parse::<f64>().unwrap_or(0.0);
+    let sign = if hemi == "S" || hemi == "W" { -1.0 } else { 1.0 };
+    format!("{:.2}", sign * (deg + min / 60.0))
+}
+
+//pireps have no day in /TM, so reference date is not used (same signature as other detectors)
+pub fn decode_pirep_at(s: &str, _reference: Option<UtcDate>) -> Option<HashMap<String, String>>{
+    decode_pirep(s)
+}
+
+//one /XX field => columns
+fn visit_field(tag: &str, value: &str, out: &mut HashMap<String, String>){
+    match tag{
+        // OKC063015 == 15nm from OKC on 063 radial, route (OKC-TUL) stays raw
+        "OV" => {
+            out.insert("location".into(), value.into());
+            // value is any text, so check digits before slicing (can't cut inside a utf-8 char)
+            let n = value.len();
+            if n >= 9 && value.get(n - 6..).is_some_and(|d| d.bytes().all(|b| b.is_ascii_digit())){
+                out.insert("location_navaid".into(), value[..n - 6].into());
+                out.insert("location_radial_deg".into(), value[n - 6..n - 3].into());
+                out.insert("location_distance_nm".into(), value[n - 3..].parse::<u32>().map_or(String::new(), |d| d.to_string()));
+            }
+        }
+        // HHMM utc, day is not sent
+        "TM" => {
+            if value.len() == 4 && value.bytes().all(|b| b.is_ascii_digit()){
+                out.insert("obs_hour".into(), value[..2].into());
+                out.insert("obs_minute".into(), value[2..].into());
+            } else{
+                push_unparsed(value, out);
+            }
+        }
+        // 085 == 8500ft, UNKN, DURC/DURD == during climb/descent
+        "FL" => match value{
+            "UNKN" => insert_missing("flight_level", out),
+            "DURC" | "DURD" => {
+                out.insert("flight_phase".into(), if value == "DURC" { "climb" } else { "descent" }.into());
+            }
+            v => match v.parse::<u32>(){
+                Ok(fl) => {
+                    out.insert("flight_level".into(), fl.to_string());
+                    out.insert("altitude_ft".into(), (fl * 100).to_string());
+                }
+                Err(_) => push_unparsed(v, out),
+            },
+        },
+        "TP" => {out.insert("aircraft_type".into(), value.into());}
+        // BKN065-TOP090 OVC120, layers go to sky[n] like in metar + top_ft
+        "SK" => {
+            for layer in value.split_whitespace(){
+                let (base, top) = match layer.split_once("-TOP"){
+                    Some((b, t)) => (b, Some(t)),
+                    None => (layer, None),
+                };
+                if SiftParser::parse(Rule::clouds, base).is_err(){
+                    push_unparsed(layer, out);
+                    continue;
+                }
+                let i = next_index(out, "sky");
+                apply_pattern("", base, SimplePattern::Cloud, out);
+                if let Some(top) = top.and_then(|t| t.parse::<u32>().ok()){
+                    out.insert(format!("sky[{i}].top_ft"), (top * 100).to_string());
+                }
+            }
+        }
+        // FV03SM == flight visibility, rest are weather groups
+        "WX" => {
+            for token in value.split_whitespace(){
+                if let Some(fv) = token.strip_prefix("FV").and_then(|t| t.strip_suffix("SM")){
+                    out.insert("flight_visibility_sm".into(), fv.parse::<u32>().map_or(fv.into(), |v| v.to_string()));
+                } else if SiftParser::parse(Rule::present_weather, token).is_ok(){
+                    apply_pattern("", token, SimplePattern::Weather, out);
+                } else{
+                    push_unparsed(token, out);
+                }
+            }
+        }
+        // M05 == -5C
+        "TA" => {
+            let t = value.replace('M', "-");
+            match t.parse::<i32>(){
+                Ok(v) => {out.insert("temp_c".into(), v.to_string());}
+                Err(_) => push_unparsed(value, out),
+            }
+        }
+        // 27045KT or 270045 (ddd + kt)
+        "WV" => {
+            let v = value.replace(' ', "");
+            let wind = if v.len() == 6 && v.bytes().all(|b| b.is_ascii_digit()){ format!("{v}KT") } else { v };
+            if SiftParser::parse(Rule::wind, &wind).is_ok(){
+                apply_pattern("", &wind, SimplePattern::Wind, out);
+            } else{
+                push_unparsed(value, out);
+            }
+        }
+        "TB" => hazard("turbulence", value, TURBULENCE_TYPES, out),
+        "IC" => hazard("icing", value, ICING_TYPES, out),
+        _ => {}
+    }
+}
+
+//TB / IC: intensity (LGT-MOD), frequency, type, levels (060-080, BLO 050, ABV 100)
+fn hazard(name: &str, value: &str, types: &[(&str, &'static str)], out: &mut HashMap<String, String>){
+    let col = |s: &str| format!("{name}_{s}");
+    let mut tokens = value.split_whitespace().peekable();
+    while let Some(token) = tokens.next(){
+        let levels = |t: &str| -> Option<Vec<u32>>{
+            t.split('-').map(|p| (p.len() == 3).then(|| p.parse::<u32>().ok()).flatten().map(|v| v * 100)).collect()
+        };
+        if let Some(intensity) = intensity(token){
+            out.insert(col("intensity"), intensity);
+        } else if let Some(f) = lookup(HAZARD_FREQUENCY, token){
+            out.insert(col("frequency"), f.into());
+        } else if let Some(t) = lookup(types, token){
+            out.insert(col("type"), t.into());
+        } else if let Some(l) = levels(token){
+            out.insert(col("base_ft"), l[0].to_string());
+            if let Some(top) = l.get(1){
+                out.insert(col("top_ft"), top.to_string());
+            }
+        } else if (token == "BLO" || token == "ABV") && let Some(l) = tokens.peek().and_then(|t| levels(t)){
+            tokens.next();
+            out.insert(col(if token == "BLO" { "top_ft" } else { "base_ft" }), l[0].to_string());
+        } else{
+            push_unparsed(token, out);
+        }
+    }
+}
+
+//LGT, MOD-SEV ... => light, moderate-severe
+fn intensity(token: &str) -> Option<String>{
+    let words: Option<Vec<&str>> = token.split('-').map(|p| lookup(INTENSITY, p)).collect();
+    words.map(|w| w.join("-"))
+}
+
+fn push_unparsed(token: &str, out: &mut HashMap<String, String>){
+    let col = out.entry("unparsed".into()).or_default();
+    if !col.is_empty(){ col.push(' '); }
+    col.push_str(token);
+}
+
+const INTENSITY: &[(&str, &str)] = &[("NEG","none"),("SMTH","smooth"),("TRC","trace"),("TRACE","trace"),("LGT","light"),
+    ("MOD","moderate"),("SEV","severe"),("HVY","heavy"),("EXTRM","extreme")];
+const HAZARD_FREQUENCY: &[(&str, &str)] = &[("OCNL","occasional"),("INTMT","intermittent"),("CONS","continuous")];
+const TURBULENCE_TYPES: &[(&str, &str)] = &[("CAT","clear air"),("CHOP","chop"),("LLWS","low-level wind shear"),("MWAVE","mountain wave")];
+const ICING_TYPES: &[(&str, &str)] = &[("RIME","rime"),("CLR","clear"),("MX","mixed"),("MXD","mixed")];
@@ -0,0 +1,291 @@
+use pest::Parser;
+use std::collections::HashMap;
+use crate::date::{UtcDate, iso_minutes};
+use crate::metar::{Rule, SiftParser, SimplePattern, apply_pattern, norm, visit_metar};
+
+//one forecast period: base forecast or one change group
+//fields use same column names as decoded metar (wind_speed, visibility_m, sky[0].cover ...)
+#[derive(Clone, Debug, Default)]
+pub struct TafPeriod {
+    pub change: String, // BASE, FM, BECMG, TEMPO, PROB
+    pub probability: Option<u32>,
+    pub start: Option<i64>, // minutes since epoch
+    pub end: Option<i64>,
+    pub fields: HashMap<String, String>,
+}
+
+//decoded taf, times are minutes since epoch (see date::iso_minutes)
+#[derive(Clone, Debug, Default)]
+pub struct Taf {
+    pub station: String,
+    pub issue_time: Option<i64>,
+    pub valid_from: Option<i64>,
+    pub valid_to: Option<i64>,
+    pub header: HashMap<String, String>, // flags, rmk.* and so on
+    pub periods: Vec<TafPeriod>,         // base forecast is always first
+}
+
+//forecast in force at one moment: prevailing fields + TEMPO/PROB (and unfinished BECMG) alternatives
+#[derive(Clone, Debug, Default)]
+pub struct TafForecast<'a> {
+    pub fields: HashMap<String, String>,
+    pub alternatives: Vec<&'a TafPeriod>,
+}
+
+impl Taf {
+    //one flat map per period: station, issue_time, period_start/end, change_type, probability + forecast fields
+    pub fn period_rows(&self) -> Vec<HashMap<String, String>>{
+        self.periods.iter().map(|p|{
+            let mut row = p.fields.clone();
+            row.insert("station".into(), self.station.clone());
+            row.insert("issue_time".into(), self.issue_time.map(iso_minutes).unwrap_or_default());
+            row.insert("period_start".into(), p.start.map(iso_minutes).unwrap_or_default());
+            row.insert("period_end".into(), p.end.map(iso_minutes).unwrap_or_default());
+            row.insert("change_type".into(), p.change.clone());
+            row.insert("probability".into(), p.probability.map(|v| v.to_string()).unwrap_or_default());
+            row
+        }).collect()
+    }
+
+    //prevailing forecast at t (minutes since epoch), None outside validity or for NIL/CNL
+    //FM replaces everything, finished BECMG replaces only groups it mentions (wind, visibility, weather, sky ...)
+    pub fn forecast_at(&self, t: i64) -> Option<TafForecast<'_>>{
+        let (Some(from), Some(to)) = (self.valid_from, self.valid_to) else{
+            return None;
+        };
+        // NIL/CNL taf forecasts nothing
+        if t < from || t >= to || self.periods.is_empty(){
+            return None;
+        }
+        let mut now = TafForecast::default();
+        let started = |p: &TafPeriod| p.start.is_some_and(|s| s <= t);
+        let running = |p: &TafPeriod| started(p) && p.end.is_none_or(|e| t < e);
+        for p in &self.periods{
+            match p.change.as_str(){
+                "BASE" => now.fields = p.fields.clone(),
+                "FM" if started(p) => now.fields = p.fields.clone(),
+                "BECMG" if p.end.is_some_and(|e| e <= t) => {
+                    let changed: Vec<&str> = p.fields.keys().map(|k| field_category(k)).collect();
+                    now.fields.retain(|k, _| !changed.contains(&field_category(k)));
+                    now.fields.extend(p.fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+                }
+                // still changing, new values are only possible
+                "BECMG" | "TEMPO" | "PROB" if running(p) => now.alternatives.push(p),
+                _ => {}
+            }
+        }
+        Some(now)
+    }
+
+    //one row per hour of validity: station, issue_time, hour, prevailing fields and alt[n].* alternatives
+    pub fn timeline(&self) -> Vec<HashMap<String, String>>{
+        let (Some(from), Some(to)) = (self.valid_from, self.valid_to) else{
+            return Vec::new();
+        };
+        (from..to).step_by(60).filter_map(|t|{
+            let now = self.forecast_at(t)?;
+            let mut row = now.fields;
+            row.insert("station".into(), self.station.clone());
+            row.insert("issue_time".into(), self.issue_time.map(iso_minutes).unwrap_or_default());
+            row.insert("hour".into(), iso_minutes(t));
+            for (i, p) in now.alternatives.iter().enumerate(){
+                let a = format!("alt[{i}]");
+                row.insert(format!("{a}.type"), p.change.clone());
+                row.insert(format!("{a}.probability"), p.probability.map(|v| v.to_string()).unwrap_or_default());
+                row.insert(format!("{a}.start"), p.start.map(iso_minutes).unwrap_or_default());
+                row.insert(format!("{a}.end"), p.end.map(iso_minutes).unwrap_or_default());
+                row.extend(p.fields.iter().map(|(k, v)| (format!("{a}.{k}"), v.clone())));
+            }
+            Some(row)
+        }).collect()
+    }
+}
+
+//which part of forecast a column belongs to, BECMG swaps whole parts
+//CAVOK/NSW columns go with visibility, sky and weather they describe
+fn field_category(key: &str) -> &str{
+    match key{
+        k if k.starts_with("wind_shear") => "wind_shear",
+        k if k.starts_with("wind_") => "wind",
+        k if k.starts_with("visibility_") || k == "cavok" => "visibility",
+        k if k.starts_with("wx[") || k == "wx_significant" => "weather",
+        k if k.starts_with("sky[") || k == "sky_clear_below_ft" || k.starts_with("vertical_visibility") => "sky",
+        k if k.starts_with("max_temp") || k.starts_with("min_temp") => "temp",
+        k => k,
+    }
+}
+
+// parse full taf string via pest into Taf
+// DDHH groups are resolved against issue time, issue time against reference (or today)
+pub fn parse_taf(s: &str, reference: Option<UtcDate>) -> Option<Taf>{
+    let s = norm(s);
+    let report = SiftParser::parse(Rule::taf_report, &s).ok()?.next()?;
+    let reference = reference.unwrap_or_else(UtcDate::today);
+    let mut taf = Taf::default();
+    for flag in ["is_correction", "is_automated", "is_amended", "nil", "cancelled"]{
+        taf.header.insert(flag.into(), "false".into());
+    }
+    // days of validity and change groups are counted from issue date
+    let mut base_date = reference;
+    for pair in report.into_inner(){
+        let text = pair.as_str();
+        match pair.as_rule(){
+            Rule::modifier => {
+                let flag = match text{
+                    "COR" => "is_correction",
+                    "AUTO" => "is_automated",
+                    _ => "is_amended",
+                };
+                taf.header.insert(flag.into(), "true".into());
+            }
+            Rule::station => taf.station = text.into(),
+            Rule::time => {
+                let num = |r: std::ops::Range<usize>| text.get(r).and_then(|v| v.parse::<u32>().ok());
+                if let (Some(d), Some(h), Some(m)) = (num(0..2), num(2..4), num(4..6))
+                    && let Some(date) = reference.resolve_day(d){
+                    taf.issue_time = Some(date.minutes(h, m));
+                    base_date = date;
+                }
+            }
+            Rule::taf_validity => {
+                (taf.valid_from, taf.valid_to) = period_minutes(text, base_date);
+            }
+            Rule::taf_nil => {taf.header.insert("nil".into(), "true".into());}
+            Rule::taf_cancelled => {taf.header.insert("cancelled".into(), "true".into());}
+            Rule::taf_base => {
+                let mut base = TafPeriod { change: "BASE".into(), start: taf.valid_from, end: taf.valid_to, ..Default::default() };
+                for g in pair.into_inner(){
+                    visit_group(&g, &mut base.fields);
+                }
+                taf.periods.push(base);
+            }
+            Rule::taf_change => taf.periods.push(change_period(pair, base_date, taf.valid_from)),
+            // groups one by one: Rule::remarks would seed sensor/maintenance flags, they only make sense for observations
+            Rule::remarks => {
+                for inner in pair.into_inner(){
+                    visit_metar(&inner, "rmk", &mut taf.header);
+                }
+            }
+            _ => {}
+        }
+    }
+    // base and each FM last until next FM or end of validity
+    let fm_starts: Vec<Option<i64>> = taf.periods.iter().filter(|p| p.change == "FM").map(|p| p.start).collect();
+    let mut next_fm = fm_starts.into_iter();
+    for p in taf.periods.iter_mut().filter(|p| p.change == "BASE" || p.change == "FM"){
+        p.end = next_fm.next().flatten().or(taf.valid_to);
+    }
+    Some(taf)
+}
+
+//same as decode_metar, but for taf: flat map with header, base forecast and change[n].* groups
+pub fn decode_taf(s: &str) -> Option<HashMap<String, String>>{
+    decode_taf_at(s, None)
+}
+
+pub fn decode_taf_at(s: &str, reference: Option<UtcDate>) -> Option<HashMap<String, String>>{
+    let taf = parse_taf(s, reference)?;
+    let mut out = taf.header.clone();
+    out.insert("station".into(), taf.station.clone());
+    for (name, t) in [("issue_time", taf.issue_time), ("valid_from", taf.valid_from), ("valid_to", taf.valid_to)]{
+        out.insert(name.into(), t.map(iso_minutes).unwrap_or_default());
+    }
+    let mut changes = 0;
+    for p in &taf.periods{
+        // base forecast goes without prefix, same as observed values in metar
+        if p.change == "BASE"{
+            out.extend(p.fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+            continue;
+        }
+        let c = format!("change[{changes}]");
+        changes += 1;
+        out.insert(format!("{c}.type"), p.change.clone());
+        out.insert(format!("{c}.probability"), p.probability.map(|v| v.to_string()).unwrap_or_default());
+        out.insert(format!("{c}.start"), p.start.map(iso_minutes).unwrap_or_default());
+        out.insert(format!("{c}.end"), p.end.map(iso_minutes).unwrap_or_default());
+        out.extend(p.fields.iter().map(|(k, v)| (format!("{c}.{k}"), v.clone())));
+    }
+    Some(out)
+}
+
+//FM/BECMG/TEMPO/PROB head + its groups
+fn change_period(pair: pest::iterators::Pair<Rule>, base_date: UtcDate, valid_from: Option<i64>) -> TafPeriod{
+    let mut p = TafPeriod::default();
+    for inner in pair.into_inner(){
+        let text = inner.as_str();
+        match inner.as_rule(){
+            // FM121800 (DDHHMM) or old FM1800 (HHMM, first such time from start of validity)
+            Rule::taf_from => {
+                p.change = "FM".into();
+                let digits = &text[2..];
+                let num = |r: std::ops::Range<usize>| digits.get(r).and_then(|v| v.parse::<u32>().ok());
+                if digits.len() == 6{
+                    if let (Some(d), Some(h), Some(m)) = (num(0..2), num(2..4), num(4..6)){
+                        p.start = base_date.resolve_day(d).map(|date| date.minutes(h, m));
+                    }
+                } else if let (Some(h), Some(m)) = (num(0..2), num(2..4)){
+                    let from = valid_from.unwrap_or_else(|| base_date.minutes(0, 0));
+                    let start = from - from.rem_euclid(24 * 60) + i64::from(h * 60 + m);
+                    p.start = Some(if start < from { start + 24 * 60 } else { start });
+                }
+            }
+            Rule::taf_becmg | Rule::taf_tempo | Rule::taf_prob => {
+                p.change = if text.contains("TEMPO") { "TEMPO" } else if text.starts_with("BECMG") { "BECMG" } else { "PROB" }.into();
+                if let Some(period) = inner.clone().into_inner().find(|i| i.as_rule() == Rule::taf_period){
+                    (p.start, p.end) = period_minutes(period.as_str(), base_date);
+                }
+                if let Some(prob) = inner.into_inner().find(|i| i.as_rule() == Rule::taf_probability){
+                    p.probability = prob.as_str().parse().ok();
+                }
+            }
+            _ => visit_group(&inner, &mut p.fields),
+        }
+    }
+    p
+}
+
+//forecast group => fields, metar groups are decoded by visit_metar
+fn visit_group(pair: &pest::iterators::Pair<Rule>, fields: &mut HashMap<String, String>){
+    let text = pair.as_str();
+    match pair.as_rule(){
+        // WS020/24045KT
+        Rule::taf_wind_shear => {
+            if let Ok(h) = text[2..5].parse::<u32>(){
+                fields.insert("wind_shear_height_ft".into(), (h * 100).to_string());
+            }
+            let mut ws = HashMap::new();
+            apply_pattern("", &text[6..], SimplePattern::Wind, &mut ws);
+            for (k, v) in ws{
+                fields.insert(format!("wind_shear_{}", k.trim_start_matches("wind_")), v);
+            }
+        }
+        // TX25/1220Z == max 25C at day 12 20Z
+        Rule::taf_temp => {
+            let kind = if text.starts_with("TX") { "max" } else { "min" };
+            if let Some((t, time)) = text[2..].split_once('/'){
+                fields.insert(format!("{kind}_temp_c"), t.replace('M', "-"));
+                fields.insert(format!("{kind}_temp_time"), time.trim_end_matches('Z').into());
+            }
+        }
+        Rule::taf_unparsed => {
+            let col = fields.entry("unparsed".into()).or_default();
+            if !col.is_empty(){ col.push(' '); }
+            col.push_str(text);
+        }
+        _ => visit_metar(pair, "", fields),
+    }
+}
+
+//DDHH/DDHH => (start, end) in minutes since epoch
+fn period_minutes(text: &str, base_date: UtcDate) -> (Option<i64>, Option<i64>){
+    let ddhh = |s: &str| -> Option<i64>{
+        let d = s.get(..2)?.parse::<u32>().ok()?;
+        let h = s.get(2..4)?.parse::<u32>().ok()?;
+        if h > 24 { return None; }
+        Some(base_date.resolve_day(d)?.minutes(h, 0))
+    };
+    match text.split_once('/'){
+        Some((a, b)) => (ddhh(a), ddhh(b)),
+        None => (None, None),
+    }
+}
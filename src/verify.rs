@@ -0,0 +1,136 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::date::{iso_minutes, parse_iso_minutes};
+use crate::metar::METRES_PER_SM;
+use crate::taf::Taf;
+
+type Rows = Vec<HashMap<String, String>>;
+
+//counters for one station, checks are (checked, hits) in CHECKS order
+#[derive(Default)]
+struct StationStats {
+    observations: u32,
+    matched: u32,
+    checks: [(u32, u32); 4],
+}
+
+//what is compared, each one is a band with same labels for metar and taf
+const CHECKS: [&str; 4] = ["category", "ceiling", "visibility", "wind"];
+
+//metar vs taf in force at obs_time
+//returns per-observation rows and per-station summary rows
+//forecast counts as hit when prevailing value or any TEMPO/PROB/BECMG alternative falls in observed band
+pub fn verify_rows(metars: &[HashMap<String, String>], tafs: &[Taf]) -> (Rows, Rows){
+    let mut rows = Vec::new();
+    let mut stats: BTreeMap<String, StationStats> = BTreeMap::new();
+    for m in metars{
+        let (Some(station), Some(t)) = (m.get("station"), m.get("obs_time").and_then(|v| parse_iso_minutes(v))) else{
+            continue;
+        };
+        let st = stats.entry(station.clone()).or_default();
+        st.observations += 1;
+        let mut row = HashMap::from([("station".to_string(), station.clone()), ("obs_time".to_string(), iso_minutes(t))]);
+        // latest taf of this station that covers obs time and was already issued then
+        let taf = tafs.iter()
+            .filter(|taf| &taf.station == station && taf.issue_time.is_none_or(|i| i <= t))
+            .filter_map(|taf| taf.forecast_at(t).map(|now| (taf, now)))
+            .max_by_key(|(taf, _)| taf.issue_time);
+        row.insert("taf_issue_time".into(), taf.as_ref().and_then(|(taf, _)| taf.issue_time).map(iso_minutes).unwrap_or_default());
+        if taf.is_some(){
+            st.matched += 1;
+        }
+        for (i, check) in CHECKS.iter().enumerate(){
+            let observed = band(check, m);
+            let forecast = taf.as_ref().and_then(|(_, now)| band(check, &now.fields));
+            row.insert(format!("observed_{check}"), observed.unwrap_or_default().into());
+            row.insert(format!("forecast_{check}"), forecast.unwrap_or_default().into());
+            let hit = match (observed, &taf){
+                (Some(o), Some((_, now))) if forecast.is_some() => {
+                    Some(forecast == Some(o) || now.alternatives.iter().any(|p| band(check, &p.fields) == Some(o)))
+                }
+                _ => None,
+            };
+            row.insert(format!("{check}_hit"), hit.map(|h| h.to_string()).unwrap_or_default());
+            if let Some(h) = hit{
+                st.checks[i].0 += 1;
+                st.checks[i].1 += h as u32;
+            }
+        }
+        rows.push(row);
+    }
+    let summary = stats.into_iter().map(|(station, st)|{
+        let mut row = HashMap::from([
+            ("station".to_string(), station),
+            ("observations".to_string(), st.observations.to_string()),
+            ("matched".to_string(), st.matched.to_string()),
+        ]);
+        for (check, (checked, hits)) in CHECKS.iter().zip(st.checks){
+            row.insert(format!("{check}_checked"), checked.to_string());
+            row.insert(format!("{check}_hits"), hits.to_string());
+            let rate = if checked == 0 { String::new() } else { format!("{:.2}", hits as f64 / checked as f64) };
+            row.insert(format!("{check}_hit_rate"), rate);
+        }
+        row
+    }).collect();
+    (rows, summary)
+}
+
+//decoded fields (metar or taf forecast) => band label, None if not enough data
+fn band(check: &str, fields: &HashMap<String, String>) -> Option<&'static str>{
+    match check{
+        "category" => flight_category(fields),
+        "ceiling" => Some(match ceiling_ft(fields)?{
+            Some(c) if c < 500 => "<500",
+            Some(c) if c < 1000 => "500-999",
+            Some(c) if c <= 3000 => "1000-3000",
+            _ => ">3000",
+        }),
+        "visibility" => Some(match visibility_sm(fields)?{
+            v if v < 1.0 => "<1SM",
+            v if v < 3.0 => "1-3SM",
+            v if v <= 5.0 => "3-5SM",
+            _ => ">5SM",
+        }),
+        "wind" => Some(match fields.get("wind_speed_kt")?.parse::<u32>().ok()?{
+            s if s < 10 => "<10KT",
+            s if s < 20 => "10-19KT",
+            s if s < 30 => "20-29KT",
+            _ => ">=30KT",
+        }),
+        _ => None,
+    }
+}
+
+// faa flight rules: LIFR ceiling <500ft or vis <1sm, IFR <1000ft or <3sm, MVFR <=3000ft or <=5sm, else VFR
+fn flight_category(fields: &HashMap<String, String>) -> Option<&'static str>{
+    let ceiling = ceiling_ft(fields)?.unwrap_or(u32::MAX);
+    let vis = visibility_sm(fields)?;
+    Some(if ceiling < 500 || vis < 1.0 {
+        "LIFR"
+    } else if ceiling < 1000 || vis < 3.0 {
+        "IFR"
+    } else if ceiling <= 3000 || vis <= 5.0 {
+        "MVFR"
+    } else {
+        "VFR"
+    })
+}
+
+//lowest broken/overcast layer or vertical visibility
+//Some(None) == sky is known, but there is no ceiling
+fn ceiling_ft(fields: &HashMap<String, String>) -> Option<Option<u32>>{
+    let known = fields.keys().any(|k| k.starts_with("sky[") || k.starts_with("vertical_visibility") || k == "sky_clear_below_ft" || k == "cavok");
+    if !known{
+        return None;
+    }
+    let layers = (0..).map_while(|i| fields.get(&format!("sky[{i}].cover")).map(|c| (i, c)))
+        .filter(|(_, c)| *c == "broken" || *c == "overcast")
+        .filter_map(|(i, _)| fields.get(&format!("sky[{i}].base_ft"))?.parse::<u32>().ok());
+    let vv = fields.get("vertical_visibility_ft").and_then(|v| v.parse::<u32>().ok());
+    Some(layers.chain(vv).min())
+}
+
+//rounded, so 3SM that went through metres is still 3
+fn visibility_sm(fields: &HashMap<String, String>) -> Option<f64>{
+    let m = fields.get("visibility_m")?.parse::<f64>().ok()?;
+    Some((m / METRES_PER_SM * 100.0).round() / 100.0)
+}
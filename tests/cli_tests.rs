@@ -0,0 +1,29 @@
+use std::fs;
+use std::process::Command;
+use json_sift_parser::verify_to_csv;
+use serde_json::json;
+
+//verify with --summary file and no -o: stdout must hold only the rows csv
+#[test]
+fn verify_summary_file_keeps_stdout_csv(){
+    let dir = std::env::temp_dir().join(format!("jsonsift_cli_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let tafs = json!([
+        { "issueTime": "2025-11-04T17:20:00.000Z", "rawTAF": "TAF KJFK 041720Z 0418/0524 24012KT P6SM BKN040" }
+    ]);
+    let metars = json!([
+        { "reportTime": "2025-11-04T21:00:00.000Z", "rawOb": "KJFK 042051Z 25010KT 10SM BKN045 12/04 A2992" }
+    ]);
+    fs::write(dir.join("m.json"), metars.to_string()).unwrap();
+    fs::write(dir.join("t.json"), tafs.to_string()).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_jsonsift"))
+        .arg("verify").arg(dir.join("m.json")).arg(dir.join("t.json"))
+        .arg("--summary").arg(dir.join("s.csv"))
+        .output().unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let (rows, summary) = verify_to_csv(&metars, &tafs, None).unwrap();
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), rows);
+    assert_eq!(fs::read_to_string(dir.join("s.csv")).unwrap(), summary);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("saved:"));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,6 +1,6 @@
 use pest::Parser;
 //the parser
-use json_sift_parser::{Rule, SiftParser, convert_to_csv};
+use json_sift_parser::{Rule, SiftParser, UtcDate, convert_taf_periods_to_csv, convert_taf_timeline_to_csv, convert_to_csv, convert_to_csv_at, decode_metar, decode_metar_at, decode_pirep, decode_taf, decode_taf_at, parse_taf, verify_to_csv};
 use serde_json::json;
 
 //cehck if metar report is parsed
@@ -89,7 +89,7 @@ fn parse_visibility_invalid(){
 //clouds
 #[test]
 fn parse_clouds_valid(){
-    for s in ["FEW020", "BKN100","OVC200","SCT030","CLR", "SKC"]{
+    for s in ["FEW020", "BKN100","OVC200","SCT030","CLR", "SKC", "NSC", "NCD"]{
         assert!(SiftParser::parse(Rule::clouds, s).is_ok(),"crushed on {s}");
     }
 }
@@ -117,14 +117,14 @@ fn parse_temp_dew_invalid() {
 // pressure
 #[test]
 fn parse_pressure_valid(){
-    for s in ["A2992","A1000","A0000"]{
+    for s in ["A2992","A1000","A0000","Q1013","Q0998"]{
         assert!(SiftParser::parse(Rule::pressure, s).is_ok(), "crushed on {s}");
     }
 }
 
 #[test]
 fn parse_pressure_invalid() {
-    for s in ["2992", "AA992","A29"]{
+    for s in ["2992", "AA992","A29","Q101"]{
         assert!(SiftParser::parse(Rule::pressure, s).is_err(),"Should crush on {s}");
     }
 }
@@ -150,7 +150,7 @@ fn parse_wind_dir(){
 
 #[test]
 fn parse_wind_units() {
-    for s in ["KT", "MPS"] {
+    for s in ["KT", "MPS", "KMH"] {
         assert!(SiftParser::parse(Rule::wind_units, s).is_ok(),"crushed on {s}");
     }
 }
@@ -204,4 +204,917 @@ fn csv_quotes_special_chars() {
     let csv = convert_to_csv(&data).unwrap();
     assert!(csv.contains("\"foo,bar\""));
     assert!(csv.contains("\"multi\nline\""));
-}
\ No newline at end of file
+}
+//station is taken from its slot in the report
+#[test]
+fn decode_station_positional(){
+    let m = decode_metar("METAR KLAX 042053Z 26010G14KT 10SM SCT040 18/12 A2985 RMK AO2").unwrap();
+    assert_eq!(m.get("station").map(String::as_str), Some("KLAX"));
+    assert_eq!(m.get("time").map(String::as_str), Some("042053Z"));
+    assert!(!m.contains_key("unparsed"));
+}
+
+//station alone or in wrong place is not a metar
+#[test]
+fn decode_station_not_a_report(){
+    assert!(decode_metar("KLAX").is_none());
+    assert!(decode_metar("TEST METAR KLAX 042053Z 26010KT").is_none());
+}
+
+//groups after the known ones are kept in unparsed
+#[test]
+fn decode_unparsed_groups(){
+    let m = decode_metar("UKBB 121200Z 18005KT 10SM FEW020 15/10 A2992 XYZ KLAX").unwrap();
+    assert_eq!(m.get("unparsed").map(String::as_str), Some("XYZ KLAX"));
+    assert_eq!(m.get("station").map(String::as_str), Some("UKBB"));
+}
+
+#[test]
+fn csv_has_metar_station(){
+    let data = json!([{ "rawOb": "METAR KORD 042053Z 19012G19KT 9SM BKN070 15/10 A2995 RMK AO2" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    assert!(csv.contains("rawOb.metar.station"));
+}
+
+//every cloud layer is kept
+#[test]
+fn decode_cloud_layers(){
+    let m = decode_metar("KLAX 042053Z 26010KT 10SM FEW020 SCT040CB BKN100 18/12 A2985").unwrap();
+    assert_eq!(m.get("sky[0].cover").map(String::as_str), Some("few"));
+    assert_eq!(m.get("sky[1].base_ft").map(String::as_str), Some("4000"));
+    assert_eq!(m.get("sky[1].type").map(String::as_str), Some("cumulonimbus"));
+    assert_eq!(m.get("sky[2].cover").map(String::as_str), Some("broken"));
+}
+
+#[test]
+fn parse_clouds_suffix_and_slashes(){
+    for s in ["BKN015TCU", "FEW020CB", "BKN///", "///015", "SCT030///"]{
+        assert!(SiftParser::parse(Rule::clouds, s).is_ok(),"crushed on {s}");
+    }
+}
+
+//heuristic path keeps the column prefix
+#[test]
+fn csv_cloud_layers_prefixed(){
+    let data = json!([{ "sky": "FEW020 BKN100" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    assert!(csv.contains("sky.sky[0].cover"));
+    assert!(csv.contains("sky.sky[1].base_ft"));
+    assert!(!csv.contains("cloud_cover"));
+}
+
+//present weather groups
+#[test]
+fn parse_present_weather_valid(){
+    for s in ["-RA", "+TSRA", "VCSH", "FZFG", "BR", "BLSN", "TS", "+FC", "SHRASN"]{
+        assert!(SiftParser::parse(Rule::present_weather, s).is_ok(),"crushed on {s}");
+    }
+}
+
+#[test]
+fn parse_present_weather_invalid(){
+    for s in ["-", "RAX", "VC", "XX"]{
+        assert!(SiftParser::parse(Rule::present_weather, s).is_err(),"Should crush on {s}");
+    }
+}
+
+#[test]
+fn decode_present_weather(){
+    let m = decode_metar("KJFK 121251Z 18010KT 2SM +TSRA BR BKN008CB 22/21 A2990").unwrap();
+    assert_eq!(m.get("wx[0].intensity").map(String::as_str), Some("heavy"));
+    assert_eq!(m.get("wx[0].descriptor").map(String::as_str), Some("thunderstorm"));
+    assert_eq!(m.get("wx[0].precipitation").map(String::as_str), Some("rain"));
+    assert_eq!(m.get("wx[0].description").map(String::as_str), Some("heavy thunderstorm with rain"));
+    assert_eq!(m.get("wx[1].obscuration").map(String::as_str), Some("mist"));
+    assert_eq!(m.get("sky[0].cover").map(String::as_str), Some("broken"));
+}
+
+#[test]
+fn decode_freezing_and_vicinity(){
+    let m = decode_metar("KBOS 121251Z 36005KT 1SM -FZDZ VCSH OVC004 M01/M02 A2990").unwrap();
+    assert_eq!(m.get("wx[0].description").map(String::as_str), Some("light freezing drizzle"));
+    assert_eq!(m.get("wx[1].proximity").map(String::as_str), Some("vicinity"));
+    assert_eq!(m.get("wx[1].description").map(String::as_str), Some("showers in the vicinity"));
+}
+
+//variable and calm wind
+#[test]
+fn parse_wind_variable(){
+    for s in ["VRB03KT", "00000KT", "VRB12G20KT"]{
+        assert!(SiftParser::parse(Rule::wind, s).is_ok(),"crushed on {s}");
+    }
+    assert!(SiftParser::parse(Rule::wind_variation, "240V300").is_ok());
+    assert!(SiftParser::parse(Rule::wind_variation, "240V30").is_err());
+}
+
+#[test]
+fn decode_wind_variable_and_calm(){
+    let m = decode_metar("EGLL 121220Z VRB03KT 240V300 10SM SKC 15/10 A2992").unwrap();
+    assert_eq!(m.get("wind_variable").map(String::as_str), Some("true"));
+    assert_eq!(m.get("wind_direction").map(String::as_str), Some(""));
+    assert_eq!(m.get("wind_var_from_deg").map(String::as_str), Some("240"));
+    assert_eq!(m.get("wind_var_to_deg").map(String::as_str), Some("300"));
+    let m = decode_metar("EGLL 121220Z 00000KT 10SM SKC 15/10 A2992").unwrap();
+    assert_eq!(m.get("wind_calm").map(String::as_str), Some("true"));
+}
+
+#[test]
+fn csv_wind_variable_tokens(){
+    let data = json!([{ "wind": "VRB03KT 240V300" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    assert!(csv.contains("wind.wind_variable"));
+    assert!(csv.contains("wind.wind_var_to_deg"));
+}
+
+//wind units kept + normalized to knots
+#[test]
+fn decode_wind_mps(){
+    let m = decode_metar("UUEE 121200Z 05007G12MPS 10SM FEW020 15/10 A2992").unwrap();
+    assert_eq!(m.get("wind_units").map(String::as_str), Some("MPS"));
+    assert_eq!(m.get("wind_speed").map(String::as_str), Some("07"));
+    assert_eq!(m.get("wind_speed_kt").map(String::as_str), Some("14"));
+    assert_eq!(m.get("wind_gust_kt").map(String::as_str), Some("23"));
+}
+
+#[test]
+fn csv_wind_kmh(){
+    let data = json!([{ "wind": "27036KMH" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    let mut lines = csv.lines();
+    let hdr: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let cell = |name: &str| row[hdr.iter().position(|h| *h == name).unwrap()];
+    assert_eq!(cell("wind.wind_units"), "KMH");
+    assert_eq!(cell("wind.wind_speed_kt"), "19");
+}
+
+//non-ascii text ending like a wind group must not panic
+#[test]
+fn csv_wind_non_ascii(){
+    let data = json!([{ "w": "ééé1KT" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    assert!(csv.contains("ééé1KT"));
+    assert!(!csv.contains("wind_speed"));
+}
+
+//metric visibility
+#[test]
+fn parse_visibility_metric(){
+    for s in ["9999", "0800", "4000NE", "6000NDV", "1500W"]{
+        assert!(SiftParser::parse(Rule::visibility_metric, s).is_ok(),"crushed on {s}");
+    }
+    for s in ["800", "4000X", "10000"]{
+        assert!(SiftParser::parse(Rule::visibility_metric, s).is_err(),"Should crush on {s}");
+    }
+}
+
+#[test]
+fn decode_visibility_metric(){
+    let m = decode_metar("LFPG 121200Z 24010KT 4000 1500SW BR BKN005 12/11 A2992").unwrap();
+    assert_eq!(m.get("visibility_m").map(String::as_str), Some("4000"));
+    assert_eq!(m.get("visibility_unit").map(String::as_str), Some("m"));
+    assert_eq!(m.get("visibility_min_m").map(String::as_str), Some("1500"));
+    assert_eq!(m.get("visibility_min_dir").map(String::as_str), Some("SW"));
+    let m = decode_metar("EDDF 121200Z 24010KT 6000NDV FEW020 12/08 A2992").unwrap();
+    assert_eq!(m.get("visibility_ndv").map(String::as_str), Some("true"));
+}
+
+//minimum visibility needs a direction, bare second group is not one
+#[test]
+fn decode_visibility_min_needs_direction(){
+    assert!(SiftParser::parse(Rule::visibility_min, "1500SW").is_ok());
+    assert!(SiftParser::parse(Rule::visibility_min, "4000").is_err());
+    for s in ["LFPG 121200Z 24010KT 9999 4000 BKN005 12/11 Q1013", "LFPG 121200Z 24010KT //// 4000 BKN005 12/11 Q1013"]{
+        let m = decode_metar(s).unwrap();
+        assert!(!m.contains_key("visibility_min_m"), "min visibility on {s}");
+        assert!(!m.contains_key("visibility_min_dir"), "min direction on {s}");
+    }
+    let t = decode_taf("TAF LFPG 121100Z 1212/1318 24010KT 4000 1500SW BR BKN005").unwrap();
+    assert_eq!(t.get("visibility_min_m").map(String::as_str), Some("1500"));
+}
+
+//outside a report metric visibility is not guessed
+#[test]
+fn csv_visibility_metric_kept_raw(){
+    let data = json!([{ "code": "9999", "dir": "1500W" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    assert_eq!(csv.lines().nth(1), Some("9999,1500W"));
+}
+
+//us and metric stations share visibility_m
+#[test]
+fn decode_visibility_sm_in_metres(){
+    let m = decode_metar("KLAX 042053Z 26010KT 1 1/2SM BR SCT040 18/12 A2985").unwrap();
+    assert_eq!(m.get("visibility_sm").map(String::as_str), Some("1 1/2"));
+    assert_eq!(m.get("visibility_unit").map(String::as_str), Some("SM"));
+    assert_eq!(m.get("visibility_m").map(String::as_str), Some("2414"));
+}
+
+//cavok expands into implied columns
+#[test]
+fn decode_cavok(){
+    let m = decode_metar("LOWW 121220Z 31012KT CAVOK 18/09 A2992").unwrap();
+    assert_eq!(m.get("cavok").map(String::as_str), Some("true"));
+    assert_eq!(m.get("visibility_m").map(String::as_str), Some("10000"));
+    assert_eq!(m.get("sky_clear_below_ft").map(String::as_str), Some("5000"));
+    assert_eq!(m.get("wx_significant").map(String::as_str), Some("false"));
+    assert!(!m.contains_key("unparsed"));
+}
+
+#[test]
+fn decode_nsc_ncd(){
+    let m = decode_metar("EDDM 121220Z 24008KT 9999 NSC 18/09 A2992").unwrap();
+    assert_eq!(m.get("sky[0].cover").map(String::as_str), Some("no significant cloud"));
+    let m = decode_metar("EDDM 121220Z AUTO 24008KT 9999 NCD 18/09 A2992").unwrap();
+    assert_eq!(m.get("sky[0].cover").map(String::as_str), Some("no cloud detected"));
+}
+
+//qnh and altimeter both give hpa + inhg
+#[test]
+fn decode_pressure_units(){
+    let m = decode_metar("EGLL 121220Z 24008KT 9999 FEW030 18/09 Q1013").unwrap();
+    assert_eq!(m.get("pressure_hpa").map(String::as_str), Some("1013.0"));
+    assert_eq!(m.get("pressure_inhg").map(String::as_str), Some("29.91"));
+    assert_eq!(m.get("pressure_unit").map(String::as_str), Some("hPa"));
+    let m = decode_metar("KLAX 042053Z 26010KT 10SM SCT040 18/12 A2985").unwrap();
+    assert_eq!(m.get("pressure_hpa").map(String::as_str), Some("1010.8"));
+    assert_eq!(m.get("pressure_inhg").map(String::as_str), Some("29.85"));
+}
+
+//runway visual range
+#[test]
+fn parse_rvr(){
+    for s in ["R28L/1200FT", "R09/0550V0800U", "R27/P2000N", "R06R/M0600FT/D"]{
+        assert!(SiftParser::parse(Rule::rvr, s).is_ok(),"crushed on {s}");
+    }
+    for s in ["R28L/120FT", "28L/1200FT", "R24/290150"]{
+        assert!(SiftParser::parse(Rule::rvr, s).is_err(),"Should crush on {s}");
+    }
+}
+
+#[test]
+fn decode_rvr(){
+    let m = decode_metar("EGLL 121220Z 24008KT 0400 R09/0550V0800U R27/P2000N FG OVC002 08/08 Q1013").unwrap();
+    assert_eq!(m.get("rvr[0].runway").map(String::as_str), Some("09"));
+    assert_eq!(m.get("rvr[0].min").map(String::as_str), Some("550"));
+    assert_eq!(m.get("rvr[0].max").map(String::as_str), Some("800"));
+    assert_eq!(m.get("rvr[0].unit").map(String::as_str), Some("M"));
+    assert_eq!(m.get("rvr[0].tendency").map(String::as_str), Some("up"));
+    assert_eq!(m.get("rvr[1].min_qualifier").map(String::as_str), Some("above"));
+    assert_eq!(m.get("rvr[1].tendency").map(String::as_str), Some("no change"));
+    let m = decode_metar("KSEA 121220Z 18008KT 1/4SM R16L/1200FT FG OVC002 08/08 A2992").unwrap();
+    assert_eq!(m.get("rvr[0].runway").map(String::as_str), Some("16L"));
+    assert_eq!(m.get("rvr[0].unit").map(String::as_str), Some("FT"));
+}
+
+//report type + modifiers
+#[test]
+fn decode_report_type_and_modifiers(){
+    let m = decode_metar("SPECI KORD 042112Z AUTO 19012KT 9SM BKN070 15/10 A2995").unwrap();
+    assert_eq!(m.get("report_type").map(String::as_str), Some("SPECI"));
+    assert_eq!(m.get("is_automated").map(String::as_str), Some("true"));
+    assert_eq!(m.get("is_correction").map(String::as_str), Some("false"));
+    let m = decode_metar("METAR COR LKPR 121200Z 24008KT 9999 FEW030 18/09 Q1013").unwrap();
+    assert_eq!(m.get("report_type").map(String::as_str), Some("METAR"));
+    assert_eq!(m.get("is_correction").map(String::as_str), Some("true"));
+    assert_eq!(m.get("is_amended").map(String::as_str), Some("false"));
+}
+
+#[test]
+fn parse_modifier(){
+    for s in ["COR", "AUTO", "AMD"]{
+        assert!(SiftParser::parse(Rule::modifier, s).is_ok(),"crushed on {s}");
+    }
+    assert!(SiftParser::parse(Rule::report_type, "SPECI").is_ok());
+    assert!(SiftParser::parse(Rule::report_type, "METARX").is_err());
+}
+
+//observation time => iso timestamp
+#[test]
+fn decode_obs_time_with_reference(){
+    let day = UtcDate::parse("2025-11-04").unwrap();
+    let m = decode_metar_at("KLAX 042053Z 26010KT 10SM SCT040 18/12 A2985", Some(day)).unwrap();
+    assert_eq!(m.get("obs_day").map(String::as_str), Some("04"));
+    assert_eq!(m.get("obs_hour").map(String::as_str), Some("20"));
+    assert_eq!(m.get("obs_minute").map(String::as_str), Some("53"));
+    assert_eq!(m.get("obs_time").map(String::as_str), Some("2025-11-04T20:53:00Z"));
+}
+
+//month and year rollover
+#[test]
+fn decode_obs_time_rollover(){
+    let jan1 = UtcDate::parse("2026-01-01T00:10:00Z").unwrap();
+    let m = decode_metar_at("KLAX 312353Z 26010KT 10SM SCT040 18/12 A2985", Some(jan1)).unwrap();
+    assert_eq!(m.get("obs_time").map(String::as_str), Some("2025-12-31T23:53:00Z"));
+    let feb28 = UtcDate::parse("2025-02-28").unwrap();
+    let m = decode_metar_at("KLAX 010002Z 26010KT 10SM SCT040 18/12 A2985", Some(feb28)).unwrap();
+    assert_eq!(m.get("obs_time").map(String::as_str), Some("2025-03-01T00:02:00Z"));
+}
+
+#[test]
+fn utc_date_parse(){
+    assert!(UtcDate::parse("2025-02-29").is_none());
+    assert!(UtcDate::parse("2024-02-29").is_some());
+    assert!(UtcDate::parse("2025-13-01").is_none());
+    assert!(UtcDate::parse("04.11.2025").is_none());
+}
+
+//reportTime next to rawOb wins over outside date
+#[test]
+fn csv_obs_time_from_sibling(){
+    let data = json!([{ "reportTime": "2025-10-31T21:00:00.000Z",
+        "rawOb": "METAR KORD 312053Z 19012KT 9SM BKN070 15/10 A2995" }]);
+    let csv = convert_to_csv_at(&data, UtcDate::parse("2024-01-15")).unwrap();
+    assert!(csv.contains("2025-10-31T20:53:00Z"));
+    let data = json!([{ "rawOb": "METAR KORD 142053Z 19012KT 9SM BKN070 15/10 A2995" }]);
+    let csv = convert_to_csv_at(&data, UtcDate::parse("2024-01-15")).unwrap();
+    assert!(csv.contains("2024-01-14T20:53:00Z"));
+}
+
+//trend sections keep their own values
+#[test]
+fn decode_trend_sections(){
+    let m = decode_metar("EGLL 121220Z 24008KT 9999 FEW030 18/09 Q1013 BECMG FM1100 25015KT TEMPO 3000 SHRA BKN012").unwrap();
+    assert_eq!(m.get("wind_speed").map(String::as_str), Some("08"));
+    assert_eq!(m.get("visibility_m").map(String::as_str), Some("10000"));
+    assert_eq!(m.get("sky[0].cover").map(String::as_str), Some("few"));
+    assert_eq!(m.get("trend[0].change").map(String::as_str), Some("BECMG"));
+    assert_eq!(m.get("trend[0].from").map(String::as_str), Some("1100"));
+    assert_eq!(m.get("trend[0].wind_speed").map(String::as_str), Some("15"));
+    assert_eq!(m.get("trend[1].change").map(String::as_str), Some("TEMPO"));
+    assert_eq!(m.get("trend[1].visibility_m").map(String::as_str), Some("3000"));
+    assert_eq!(m.get("trend[1].wx[0].description").map(String::as_str), Some("rain showers"));
+    assert_eq!(m.get("trend[1].sky[0].base_ft").map(String::as_str), Some("1200"));
+    assert!(!m.contains_key("sky[1].cover"));
+}
+
+#[test]
+fn decode_trend_nosig(){
+    let m = decode_metar("EDDF 121220Z 24008KT CAVOK 18/09 Q1013 NOSIG").unwrap();
+    assert_eq!(m.get("trend[0].change").map(String::as_str), Some("NOSIG"));
+}
+
+#[test]
+fn parse_trend_time(){
+    for s in ["FM1100", "TL1230", "AT0900"]{
+        assert!(SiftParser::parse(Rule::trend_time, s).is_ok(),"crushed on {s}");
+    }
+    assert!(SiftParser::parse(Rule::trend_time, "FM11").is_err());
+}
+
+//remarks: station type, slp, hourly temp, maintenance
+#[test]
+fn decode_remarks_basic(){
+    let m = decode_metar("KLAX 042053Z 26010KT 10SM SCT040 18/12 A2985 RMK AO2 SLP106 T01780122 $").unwrap();
+    assert_eq!(m.get("rmk.station_type").map(String::as_str), Some("AO2"));
+    assert_eq!(m.get("rmk.slp_hpa").map(String::as_str), Some("1010.6"));
+    assert_eq!(m.get("rmk.temp_c").map(String::as_str), Some("17.8"));
+    assert_eq!(m.get("rmk.dewpoint_c").map(String::as_str), Some("12.2"));
+    assert_eq!(m.get("rmk.maintenance").map(String::as_str), Some("true"));
+    assert!(!m.contains_key("rmk.unparsed"));
+}
+
+#[test]
+fn decode_remarks_negative_and_unparsed(){
+    let m = decode_metar("KFAI 042053Z 00000KT 10SM CLR M17/M21 A3012 RMK AO1 SLP982 T11721206 SOME TEXT").unwrap();
+    assert_eq!(m.get("rmk.slp_hpa").map(String::as_str), Some("998.2"));
+    assert_eq!(m.get("rmk.temp_c").map(String::as_str), Some("-17.2"));
+    assert_eq!(m.get("rmk.dewpoint_c").map(String::as_str), Some("-20.6"));
+    assert_eq!(m.get("rmk.maintenance").map(String::as_str), Some("false"));
+    assert_eq!(m.get("rmk.unparsed").map(String::as_str), Some("SOME TEXT"));
+}
+
+//remarks: precipitation and extreme temps
+#[test]
+fn decode_remarks_precip_and_extremes(){
+    let m = decode_metar("KORD 041151Z 19012KT 9SM -RA BKN070 15/10 A2995 RMK AO2 P0015 60042 70125 10172 20094 401720094 4/005").unwrap();
+    assert_eq!(m.get("rmk.precip_1h_in").map(String::as_str), Some("0.15"));
+    assert_eq!(m.get("rmk.precip_3_6h_in").map(String::as_str), Some("0.42"));
+    assert_eq!(m.get("rmk.precip_3_6h_period_h").map(String::as_str), Some("6"));
+    assert_eq!(m.get("rmk.precip_24h_in").map(String::as_str), Some("1.25"));
+    assert_eq!(m.get("rmk.max_temp_6h_c").map(String::as_str), Some("17.2"));
+    assert_eq!(m.get("rmk.min_temp_6h_c").map(String::as_str), Some("9.4"));
+    assert_eq!(m.get("rmk.max_temp_24h_c").map(String::as_str), Some("17.2"));
+    assert_eq!(m.get("rmk.min_temp_24h_c").map(String::as_str), Some("9.4"));
+    assert_eq!(m.get("rmk.snow_depth_in").map(String::as_str), Some("5"));
+    assert!(!m.contains_key("rmk.unparsed"));
+}
+
+#[test]
+fn decode_remarks_precip_trace_and_missing(){
+    let m = decode_metar("KORD 040851Z 19012KT 9SM BKN070 15/10 A2995 RMK AO2 P0000 6//// 21012").unwrap();
+    assert_eq!(m.get("rmk.precip_1h_trace").map(String::as_str), Some("true"));
+    assert_eq!(m.get("rmk.precip_3_6h_in").map(String::as_str), Some(""));
+    assert_eq!(m.get("rmk.precip_3_6h_period_h").map(String::as_str), Some("3"));
+    assert_eq!(m.get("rmk.min_temp_6h_c").map(String::as_str), Some("-1.2"));
+}
+
+//remarks: peak wind, wind shift, pressure tendency
+#[test]
+fn decode_remarks_wind_and_pressure(){
+    let m = decode_metar("KDEN 041953Z 30020G30KT 8SM OVC090 10/02 A3012 RMK AO2 PK WND 28045/1915 WSHFT 30 FROPA 58033 PRESFR").unwrap();
+    assert_eq!(m.get("rmk.peak_wind_dir").map(String::as_str), Some("280"));
+    assert_eq!(m.get("rmk.peak_wind_speed_kt").map(String::as_str), Some("45"));
+    assert_eq!(m.get("rmk.peak_wind_time").map(String::as_str), Some("1915"));
+    assert_eq!(m.get("rmk.wind_shift_time").map(String::as_str), Some("1930"));
+    assert_eq!(m.get("rmk.frontal_passage").map(String::as_str), Some("true"));
+    assert_eq!(m.get("rmk.pressure_tendency_code").map(String::as_str), Some("8"));
+    assert_eq!(m.get("rmk.pressure_change_3h_hpa").map(String::as_str), Some("-3.3"));
+    assert_eq!(m.get("rmk.pressure_falling_rapidly").map(String::as_str), Some("true"));
+    assert!(!m.contains_key("rmk.unparsed"));
+}
+
+#[test]
+fn parse_remarks_wind_groups(){
+    for s in ["PK WND 28045/1955", "PK WND 270100/05"]{
+        assert!(SiftParser::parse(Rule::rmk_peak_wind, s).is_ok(),"crushed on {s}");
+    }
+    for s in ["WSHFT 1715", "WSHFT 1715 FROPA"]{
+        assert!(SiftParser::parse(Rule::rmk_wind_shift, s).is_ok(),"crushed on {s}");
+    }
+    assert!(SiftParser::parse(Rule::rmk_pressure_tendency, "59012").is_err());
+}
+
+//remarks: sensor status, lightning, begin/end
+#[test]
+fn decode_remarks_sensors_and_convection(){
+    let m = decode_metar("KMIA 041953Z 09012KT 5SM TSRA BKN030CB 28/24 A2995 RMK AO2 OCNL LTGICCG DSNT NW TSB05E30 RAB15E42 PNO VISNO RWY06").unwrap();
+    assert_eq!(m.get("rmk.lightning_frequency").map(String::as_str), Some("occasional"));
+    assert_eq!(m.get("rmk.lightning_types").map(String::as_str), Some("IC;CG"));
+    assert_eq!(m.get("rmk.lightning_distance").map(String::as_str), Some("distant"));
+    assert_eq!(m.get("rmk.lightning_direction").map(String::as_str), Some("NW"));
+    assert_eq!(m.get("rmk.wx_event[0].code").map(String::as_str), Some("TS"));
+    assert_eq!(m.get("rmk.wx_event[0].begin").map(String::as_str), Some("1905"));
+    assert_eq!(m.get("rmk.wx_event[0].end").map(String::as_str), Some("1930"));
+    assert_eq!(m.get("rmk.wx_event[1].description").map(String::as_str), Some("rain"));
+    assert_eq!(m.get("rmk.sensor_precip_offline").map(String::as_str), Some("true"));
+    assert_eq!(m.get("rmk.sensor_vis_offline_location").map(String::as_str), Some("RWY06"));
+    assert_eq!(m.get("rmk.sensor_ts_offline").map(String::as_str), Some("false"));
+    assert!(!m.contains_key("rmk.unparsed"));
+}
+
+#[test]
+fn decode_remarks_tsno(){
+    let m = decode_metar("KMIA 041953Z 09012KT 10SM FEW030 28/24 A2995 RMK AO2 TSNO").unwrap();
+    assert_eq!(m.get("rmk.sensor_ts_offline").map(String::as_str), Some("true"));
+}
+
+#[test]
+fn parse_remarks_lightning(){
+    for s in ["OCNL LTGICCG DSNT NW", "FRQ LTG VC ALQDS", "LTGCG OHD N-NE AND SW", "LTG DSNT"]{
+        assert!(SiftParser::parse(Rule::rmk_lightning, s).is_ok(),"crushed on {s}");
+    }
+}
+
+//recent weather and wind shear
+#[test]
+fn decode_recent_weather_and_wind_shear(){
+    let m = decode_metar("EGLL 121220Z 24015KT 9999 FEW030 18/09 Q1013 RETSRA RERA WS R27L WS ALL RWY NOSIG").unwrap();
+    assert_eq!(m.get("recent_wx[0].description").map(String::as_str), Some("thunderstorm with rain"));
+    assert_eq!(m.get("recent_wx[1].precipitation").map(String::as_str), Some("rain"));
+    assert_eq!(m.get("recent_wx[1].intensity").map(String::as_str), Some(""));
+    assert_eq!(m.get("wind_shear[0].runway").map(String::as_str), Some("27L"));
+    assert_eq!(m.get("wind_shear[1].runway").map(String::as_str), Some("ALL"));
+    assert_eq!(m.get("trend[0].change").map(String::as_str), Some("NOSIG"));
+    assert!(!m.contains_key("wx[0].code"));
+}
+
+#[test]
+fn parse_wind_shear(){
+    for s in ["WS R27", "WS RWY27L", "WS TKOF RWY20", "WS ALL RWY"]{
+        assert!(SiftParser::parse(Rule::wind_shear, s).is_ok(),"crushed on {s}");
+    }
+    assert!(SiftParser::parse(Rule::recent_weather, "RE").is_err());
+}
+
+//vertical visibility + slash groups
+#[test]
+fn decode_vertical_visibility(){
+    let m = decode_metar("KSFO 041053Z 00000KT 1/4SM FG VV002 12/12 A2992").unwrap();
+    assert_eq!(m.get("vertical_visibility_ft").map(String::as_str), Some("200"));
+}
+
+#[test]
+fn decode_missing_groups(){
+    let m = decode_metar("KXYZ 041053Z AUTO /////KT ////SM //////CB M/M A////").unwrap();
+    for col in ["wind_speed", "visibility_sm", "sky[0].cover", "sky[0].base_ft", "temp_c", "dewpoint_c", "pressure_hpa"]{
+        assert_eq!(m.get(col).map(String::as_str), Some(""), "{col}");
+        assert_eq!(m.get(&format!("{col}_missing")).map(String::as_str), Some("true"), "{col}_missing");
+    }
+    assert_eq!(m.get("sky[0].type").map(String::as_str), Some("cumulonimbus"));
+    assert!(!m.contains_key("unparsed"));
+    //only one half missing
+    let m = decode_metar("KXYZ 041053Z AUTO 24010KT 10SM CLR 12/// A2992").unwrap();
+    assert_eq!(m.get("temp_c").map(String::as_str), Some("12"));
+    assert_eq!(m.get("dewpoint_c_missing").map(String::as_str), Some("true"));
+    let m = decode_metar("KXYZ 041053Z AUTO 24010KT 10SM CLR ///// A2992").unwrap();
+    for col in ["temp_c", "dewpoint_c"]{
+        assert_eq!(m.get(&format!("{col}_missing")).map(String::as_str), Some("true"), "{col}_missing");
+    }
+    assert!(!m.contains_key("tempdew_raw"));
+}
+
+#[test]
+fn parse_missing_groups(){
+    assert!(SiftParser::parse(Rule::wind, "/////KT").is_ok());
+    assert!(SiftParser::parse(Rule::visibility, "////SM").is_ok());
+    assert!(SiftParser::parse(Rule::temp_dew, "M/M").is_ok());
+    assert!(SiftParser::parse(Rule::pressure, "A////").is_ok());
+    assert!(SiftParser::parse(Rule::vertical_visibility, "VV///").is_ok());
+    assert!(SiftParser::parse(Rule::vertical_visibility, "VV02").is_err());
+}
+
+#[test]
+fn csv_missing_tokens(){
+    let data = json!([{ "obs": "/////KT M/M" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    assert!(csv.contains("obs.wind_speed_missing"));
+    assert!(csv.contains("obs.temp_c_missing"));
+}
+
+//runway state and sea state
+#[test]
+fn decode_runway_state(){
+    let m = decode_metar("ULLI 121200Z 24008MPS 9999 -SN BKN020 M05/M08 Q1013 R24/450155 R88/CLRD93").unwrap();
+    assert_eq!(m.get("runway_state[0].runway").map(String::as_str), Some("24"));
+    assert_eq!(m.get("runway_state[0].deposit").map(String::as_str), Some("dry snow"));
+    assert_eq!(m.get("runway_state[0].extent").map(String::as_str), Some("26-50%"));
+    assert_eq!(m.get("runway_state[0].depth_mm").map(String::as_str), Some("1"));
+    assert_eq!(m.get("runway_state[0].braking_coefficient").map(String::as_str), Some("0.55"));
+    assert_eq!(m.get("runway_state[1].runway").map(String::as_str), Some("all"));
+    assert_eq!(m.get("runway_state[1].cleared").map(String::as_str), Some("true"));
+    assert_eq!(m.get("runway_state[1].braking_action").map(String::as_str), Some("medium"));
+}
+
+#[test]
+fn decode_runway_state_missing_parts(){
+    let m = decode_metar("EFHK 121200Z 24008KT 9999 BKN020 M05/M08 Q1013 R04L/29//// R/SNOCLO").unwrap();
+    assert_eq!(m.get("runway_state[0].runway").map(String::as_str), Some("04L"));
+    assert_eq!(m.get("runway_state[0].depth_mm_missing").map(String::as_str), Some("true"));
+    assert_eq!(m.get("runway_state[0].braking_action_missing").map(String::as_str), Some("true"));
+    assert_eq!(m.get("runway_state[1].snow_closed").map(String::as_str), Some("true"));
+}
+
+#[test]
+fn decode_sea_state(){
+    let m = decode_metar("LGAV 121200Z 36015KT 9999 FEW020 18/09 Q1013 W15/S3").unwrap();
+    assert_eq!(m.get("sea_surface_temp_c").map(String::as_str), Some("15"));
+    assert_eq!(m.get("sea_state").map(String::as_str), Some("slight"));
+    let m = decode_metar("ENZV 121200Z 36015KT 9999 FEW020 08/05 Q1013 WM01/H25").unwrap();
+    assert_eq!(m.get("sea_surface_temp_c").map(String::as_str), Some("-01"));
+    assert_eq!(m.get("wave_height_m").map(String::as_str), Some("2.5"));
+}
+
+#[test]
+fn csv_sea_state_token(){
+    let data = json!([{ "sea": "W15/S3", "td": "M02/M05" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    let mut lines = csv.lines();
+    let hdr: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let cell = |name: &str| hdr.iter().position(|h| *h == name).map(|i| row[i]);
+    assert_eq!(cell("sea.sea_surface_temp_c"), Some("15"));
+    assert_eq!(cell("sea.sea_state"), Some("slight"));
+    assert_eq!(cell("sea.temp_c"), None);
+    assert_eq!(cell("td.temp_c"), Some("-02"));
+}
+
+//military colour states
+#[test]
+fn decode_colour_state(){
+    let m = decode_metar("EGVN 121150Z 24012KT 9999 FEW030 14/08 Q1013 BLU BLU NOSIG").unwrap();
+    assert_eq!(m.get("colour_state").map(String::as_str), Some("BLU"));
+    assert_eq!(m.get("colour_state_forecast").map(String::as_str), Some("BLU"));
+    assert_eq!(m.get("airfield_closed").map(String::as_str), Some("false"));
+    assert_eq!(m.get("colour_min_visibility_m").map(String::as_str), Some("8000"));
+    let m = decode_metar("EGXC 121150Z 24012KT 3000 BR BKN006 10/09 Q1013 TEMPO 1500 BLACKYLO1 AMB").unwrap();
+    assert_eq!(m.get("colour_state").map(String::as_str), Some("YLO1"));
+    assert_eq!(m.get("airfield_closed").map(String::as_str), Some("true"));
+    assert_eq!(m.get("colour_state_forecast").map(String::as_str), Some("AMB"));
+    assert_eq!(m.get("trend[0].visibility_m").map(String::as_str), Some("1500"));
+    assert!(!m.contains_key("trend[0].unparsed"));
+}
+
+//taf detector
+#[test]
+fn parse_taf_groups(){
+    assert!(SiftParser::parse(Rule::taf_period, "0418/0524").is_ok());
+    assert!(SiftParser::parse(Rule::taf_from, "FM042200").is_ok());
+    assert!(SiftParser::parse(Rule::taf_prob, "PROB30 TEMPO 0506/0510").is_ok());
+    assert!(SiftParser::parse(Rule::taf_prob, "PROB50 0506/0510").is_err());
+}
+
+#[test]
+fn decode_taf_header_and_base(){
+    let d = UtcDate::parse("2025-11-04").unwrap();
+    let m = decode_taf_at("TAF AMD KJFK 041720Z 0418/0524 24012G20KT P6SM -RA BKN040 FM042200 30015KT 6SM BR SCT015", Some(d)).unwrap();
+    assert_eq!(m.get("station").map(String::as_str), Some("KJFK"));
+    assert_eq!(m.get("is_amended").map(String::as_str), Some("true"));
+    assert_eq!(m.get("issue_time").map(String::as_str), Some("2025-11-04T17:20:00Z"));
+    assert_eq!(m.get("valid_from").map(String::as_str), Some("2025-11-04T18:00:00Z"));
+    assert_eq!(m.get("valid_to").map(String::as_str), Some("2025-11-06T00:00:00Z"));
+    assert_eq!(m.get("wind_speed_kt").map(String::as_str), Some("12"));
+    assert_eq!(m.get("wx[0].code").map(String::as_str), Some("-RA"));
+    assert_eq!(m.get("sky[0].base_ft").map(String::as_str), Some("4000"));
+    //change groups never overwrite the base forecast
+    assert_eq!(m.get("wind_direction").map(String::as_str), Some("240"));
+    assert_eq!(m.get("change[0].type").map(String::as_str), Some("FM"));
+    assert_eq!(m.get("change[0].wind_direction").map(String::as_str), Some("300"));
+}
+
+#[test]
+fn decode_taf_auto_is_not_amended(){
+    let m = decode_taf("TAF AUTO KJFK 041720Z 0418/0524 24012KT P6SM BKN040").unwrap();
+    assert_eq!(m.get("is_automated").map(String::as_str), Some("true"));
+    assert_eq!(m.get("is_amended").map(String::as_str), Some("false"));
+    assert_eq!(m.get("is_correction").map(String::as_str), Some("false"));
+}
+
+//old FMhhmm is the first such time inside validity, not the issue day
+#[test]
+fn decode_taf_fm_hhmm_after_midnight(){
+    let d = UtcDate::parse("2025-11-04").unwrap();
+    let taf = parse_taf("TAF KJFK 042330Z 0500/0606 24012KT P6SM BKN040 FM0200 30015KT 6SM BR SCT015 FM2300 32010KT P6SM SKC", Some(d)).unwrap();
+    let t = |day: u32, hour: u32| UtcDate { year: 2025, month: 11, day }.minutes(hour, 0);
+    assert_eq!(taf.valid_from, Some(t(5, 0)));
+    assert_eq!(taf.periods[1].start, Some(t(5, 2)));
+    assert_eq!(taf.periods[2].start, Some(t(5, 23)));
+    //base lasts until first FM
+    assert_eq!(taf.periods[0].end, Some(t(5, 2)));
+    assert_eq!(taf.periods[1].end, Some(t(5, 23)));
+}
+
+#[test]
+fn decode_taf_remarks(){
+    let m = decode_taf("TAF KJFK 041720Z 0418/0524 24012KT P6SM BKN040 RMK AO2 NXT FCST BY 00Z").unwrap();
+    assert_eq!(m.get("rmk.station_type").map(String::as_str), Some("AO2"));
+    //sensor status is not known from a forecast
+    assert!(!m.contains_key("rmk.maintenance"));
+    assert!(!m.keys().any(|k| k.ends_with("_offline")));
+}
+
+#[test]
+fn decode_taf_rejects_metar(){
+    assert!(decode_taf("KJFK 041751Z 24012KT 10SM BKN040 12/04 A2992").is_none());
+    assert!(decode_metar("TAF KJFK 041720Z 0418/0524 24012KT P6SM BKN040").is_none());
+}
+
+#[test]
+fn csv_taf_columns(){
+    let data = json!([{ "issueTime": "2025-11-04T17:20:00.000Z", "rawTAF": "TAF EGLL 041700Z 0418/0524 22010KT 9999 SCT030 TX15/0514Z TN08/0506Z" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let cell = |name: &str| header.iter().position(|h| *h == name).map(|i| row[i]);
+    assert_eq!(cell("rawTAF.taf.station"), Some("EGLL"));
+    assert_eq!(cell("rawTAF.taf.valid_from"), Some("2025-11-04T18:00:00Z"));
+    assert_eq!(cell("rawTAF.taf.max_temp_c"), Some("15"));
+    assert_eq!(cell("rawTAF.taf.min_temp_time"), Some("0506"));
+    assert!(cell("rawTAF.metar.station").is_none());
+}
+
+//taf periods mode
+#[test]
+fn taf_period_rows(){
+    let d = UtcDate::parse("2025-11-04").unwrap();
+    let taf = parse_taf("TAF KJFK 041720Z 0418/0524 24012KT P6SM BKN040 FM042200 30015KT 6SM BR SCT015 TEMPO 0500/0504 3SM -SHRA BKN010 PROB30 0506/0510 1SM TSRA OVC008CB FM051800 VRB05KT P6SM SKC", Some(d)).unwrap();
+    let rows = taf.period_rows();
+    let cell = |i: usize, name: &str| rows[i].get(name).map(String::as_str);
+    assert_eq!(rows.len(), 5);
+    assert_eq!(cell(0, "change_type"), Some("BASE"));
+    //base and FM end where the next FM starts
+    assert_eq!(cell(0, "period_end"), Some("2025-11-04T22:00:00Z"));
+    assert_eq!(cell(1, "period_end"), Some("2025-11-05T18:00:00Z"));
+    assert_eq!(cell(4, "period_end"), Some("2025-11-06T00:00:00Z"));
+    assert_eq!(cell(2, "change_type"), Some("TEMPO"));
+    assert_eq!(cell(2, "wx[0].code"), Some("-SHRA"));
+    assert_eq!(cell(3, "change_type"), Some("PROB"));
+    assert_eq!(cell(3, "probability"), Some("30"));
+    assert_eq!(cell(3, "period_start"), Some("2025-11-05T06:00:00Z"));
+    assert_eq!(cell(3, "sky[0].type"), Some("cumulonimbus"));
+    assert!(rows.iter().all(|r| r.get("station").map(String::as_str) == Some("KJFK")));
+}
+
+#[test]
+fn csv_taf_periods(){
+    let data = json!([
+        { "issueTime": "2025-11-04T17:20:00.000Z", "rawTAF": "TAF EGLL 041700Z 0418/0524 22010KT 9999 SCT030 BECMG 0502/0504 27015KT" },
+        { "rawOb": "EGLL 041750Z 22010KT 9999 SCT030 12/08 Q1013" }
+    ]);
+    let csv = convert_taf_periods_to_csv(&data, None).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    let header: Vec<&str> = lines[0].split(',').collect();
+    let row: Vec<&str> = lines[2].split(',').collect();
+    let cell = |name: &str| header.iter().position(|h| *h == name).map(|i| row[i]);
+    assert_eq!(cell("change_type"), Some("BECMG"));
+    assert_eq!(cell("period_end"), Some("2025-11-05T04:00:00Z"));
+    assert_eq!(cell("wind_direction"), Some("270"));
+    assert!(convert_taf_periods_to_csv(&json!("TAF"), None).is_err());
+    let err = convert_taf_periods_to_csv(&json!([{ "rawOb": "EGLL 041750Z 22010KT 9999 SCT030 12/08 Q1013" }]), None).unwrap_err();
+    assert_eq!(err.to_string(), "Structure: no TAF found");
+}
+
+//taf timeline
+#[test]
+fn taf_forecast_at(){
+    let d = UtcDate::parse("2025-11-04").unwrap();
+    let taf = parse_taf("TAF EGLL 041700Z 0418/0524 22010KT 9999 -RA BKN012 FM050000 27015KT 8000 SCT020 TEMPO 0502/0506 3000 SHRA BECMG 0508/0510 31020KT NSW", Some(d)).unwrap();
+    let t = |day: u32, hour: u32| UtcDate { year: 2025, month: 11, day }.minutes(hour, 0);
+    let get = |at: i64, name: &str| taf.forecast_at(at).unwrap().fields.get(name).cloned();
+    assert_eq!(get(t(4, 18), "wind_direction").as_deref(), Some("220"));
+    //FM drops everything from the base forecast
+    assert_eq!(get(t(5, 1), "wind_direction").as_deref(), Some("270"));
+    assert_eq!(get(t(5, 1), "wx[0].code"), None);
+    let tempo = taf.forecast_at(t(5, 3)).unwrap();
+    assert_eq!(tempo.alternatives.len(), 1);
+    assert_eq!(tempo.alternatives[0].change, "TEMPO");
+    assert_eq!(tempo.fields.get("visibility_m").map(String::as_str), Some("8000"));
+    //BECMG while changing is an alternative, after it only its groups are replaced
+    assert_eq!(taf.forecast_at(t(5, 9)).unwrap().alternatives[0].change, "BECMG");
+    assert_eq!(get(t(5, 10), "wind_direction").as_deref(), Some("310"));
+    assert_eq!(get(t(5, 10), "visibility_m").as_deref(), Some("8000"));
+    assert_eq!(get(t(5, 10), "sky[0].cover").as_deref(), Some("scattered"));
+    assert!(taf.forecast_at(t(6, 0)).is_none());
+    assert!(taf.forecast_at(t(4, 17)).is_none());
+}
+
+#[test]
+fn csv_taf_timeline(){
+    let data = json!({ "issueTime": "2025-11-04T17:20:00.000Z", "rawTAF": "TAF EGLL 041700Z 0418/0506 22010KT 9999 SCT030 PROB40 0500/0503 0800 FG" });
+    let csv = convert_taf_timeline_to_csv(&data, None).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 13);
+    let header: Vec<&str> = lines[0].split(',').collect();
+    let row: Vec<&str> = lines[7].split(',').collect();
+    let cell = |name: &str| header.iter().position(|h| *h == name).map(|i| row[i]);
+    assert_eq!(cell("hour"), Some("2025-11-05T00:00:00Z"));
+    assert_eq!(cell("visibility_m"), Some("10000"));
+    assert_eq!(cell("alt[0].probability"), Some("40"));
+    assert_eq!(cell("alt[0].visibility_m"), Some("800"));
+    //NIL taf has no hours
+    let nil = json!([{ "rawTAF": "TAF EGLL 041700Z 0418/0524 NIL" }]);
+    assert!(convert_taf_timeline_to_csv(&nil, None).is_err());
+}
+
+//metar vs taf verification
+#[test]
+fn verify_metar_against_taf(){
+    let tafs = json!([
+        { "issueTime": "2025-11-04T17:20:00.000Z", "rawTAF": "TAF KJFK 041720Z 0418/0524 24012KT P6SM BKN040 TEMPO 0500/0504 2SM BR OVC008" }
+    ]);
+    let metars = json!([
+        { "reportTime": "2025-11-04T21:00:00.000Z", "rawOb": "KJFK 042051Z 25010KT 10SM BKN045 12/04 A2992" },
+        { "reportTime": "2025-11-05T01:00:00.000Z", "rawOb": "KJFK 050051Z 24012KT 2SM BR OVC007 10/09 A2990" },
+        { "reportTime": "2025-11-05T03:00:00.000Z", "rawOb": "KJFK 050251Z 24025KT 10SM FEW250 10/04 A2990" },
+        { "reportTime": "2025-11-05T03:00:00.000Z", "rawOb": "KLGA 050251Z 24012KT 10SM FEW250 10/04 A2990" }
+    ]);
+    let (rows, summary) = verify_to_csv(&metars, &tafs, None).unwrap();
+    let table = |csv: &str| -> Vec<std::collections::HashMap<String, String>>{
+        let mut lines = csv.lines();
+        let header: Vec<String> = lines.next().unwrap().split(',').map(String::from).collect();
+        lines.map(|l| header.iter().cloned().zip(l.split(',').map(String::from)).collect()).collect()
+    };
+    let rows = table(&rows);
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows[0]["observed_category"], "VFR");
+    assert_eq!(rows[0]["category_hit"], "true");
+    //tempo alternative covers the IFR hour
+    assert_eq!(rows[1]["observed_category"], "IFR");
+    assert_eq!(rows[1]["forecast_category"], "VFR");
+    assert_eq!(rows[1]["category_hit"], "true");
+    assert_eq!(rows[1]["visibility_hit"], "true");
+    assert_eq!(rows[2]["observed_wind"], "20-29KT");
+    assert_eq!(rows[2]["wind_hit"], "false");
+    //no taf for this station
+    assert_eq!(rows[3]["taf_issue_time"], "");
+    assert_eq!(rows[3]["category_hit"], "");
+    let summary = table(&summary);
+    assert_eq!(summary[0]["station"], "KJFK");
+    assert_eq!(summary[0]["observations"], "3");
+    assert_eq!(summary[0]["wind_hit_rate"], "0.67");
+    assert_eq!(summary[1]["matched"], "0");
+}
+
+//amendment issued after the observation must not be scored against it
+#[test]
+fn verify_ignores_later_amendment(){
+    let tafs = json!([
+        { "issueTime": "2025-11-04T17:20:00.000Z", "rawTAF": "TAF KJFK 041720Z 0418/0524 24012KT P6SM BKN040" },
+        { "issueTime": "2025-11-04T20:15:00.000Z", "rawTAF": "TAF AMD KJFK 042015Z 0420/0524 24025KT P6SM BKN040" }
+    ]);
+    let metars = json!([
+        { "reportTime": "2025-11-04T20:05:00.000Z", "rawOb": "KJFK 042005Z 24025KT 10SM BKN045 12/04 A2992" },
+        { "reportTime": "2025-11-04T21:00:00.000Z", "rawOb": "KJFK 042051Z 24025KT 10SM BKN045 12/04 A2992" }
+    ]);
+    let (rows, _) = verify_to_csv(&metars, &tafs, None).unwrap();
+    let mut lines = rows.lines();
+    let hdr: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let col = hdr.iter().position(|h| *h == "taf_issue_time").unwrap();
+    let wind = hdr.iter().position(|h| *h == "wind_hit").unwrap();
+    let rows: Vec<Vec<&str>> = lines.map(|l| l.split(',').collect()).collect();
+    assert_eq!(rows[0][col], "2025-11-04T17:20:00Z");
+    assert_eq!(rows[0][wind], "false");
+    assert_eq!(rows[1][col], "2025-11-04T20:15:00Z");
+    assert_eq!(rows[1][wind], "true");
+}
+
+//pirep detector
+#[test]
+fn parse_pirep_fields(){
+    assert!(SiftParser::parse(Rule::pirep_report, "UA /OV OKC063015/TM 1522/FL085/TP C172").is_ok());
+    assert!(SiftParser::parse(Rule::pirep_report, "DEN UUA /OV DEN/TM 0105/FLUNKN/TP B737/TB SEV").is_ok());
+    assert!(SiftParser::parse(Rule::pirep_report, "KOKC 121200Z 18005KT 10SM FEW020 15/10 A2992").is_err());
+    assert!(SiftParser::parse(Rule::pirep_report, "DEN UA").is_err());
+    assert!(SiftParser::parse(Rule::pirep_report, "UA /TP C172").is_err());
+}
+
+#[test]
+fn decode_pirep_groups(){
+    let m = decode_pirep("OKC UA /OV OKC063015/TM 1522/FL085/TP C172/SK BKN065-TOP090/WX FV03SM HZ/TA M05/WV 27045KT/TB OCNL LGT-MOD CHOP 060-080/IC LGT RIME BLO 070/RM SMOOTH ABV/BLO").unwrap();
+    assert_eq!(m.get("station").map(String::as_str), Some("OKC"));
+    assert_eq!(m.get("is_urgent").map(String::as_str), Some("false"));
+    assert_eq!(m.get("location_navaid").map(String::as_str), Some("OKC"));
+    assert_eq!(m.get("location_radial_deg").map(String::as_str), Some("063"));
+    assert_eq!(m.get("location_distance_nm").map(String::as_str), Some("15"));
+    assert_eq!(m.get("obs_hour").map(String::as_str), Some("15"));
+    assert_eq!(m.get("altitude_ft").map(String::as_str), Some("8500"));
+    assert_eq!(m.get("aircraft_type").map(String::as_str), Some("C172"));
+    assert_eq!(m.get("sky[0].cover").map(String::as_str), Some("broken"));
+    assert_eq!(m.get("sky[0].top_ft").map(String::as_str), Some("9000"));
+    assert_eq!(m.get("flight_visibility_sm").map(String::as_str), Some("3"));
+    assert_eq!(m.get("wx[0].description").map(String::as_str), Some("haze"));
+    assert_eq!(m.get("temp_c").map(String::as_str), Some("-5"));
+    assert_eq!(m.get("wind_speed_kt").map(String::as_str), Some("45"));
+    assert_eq!(m.get("turbulence_intensity").map(String::as_str), Some("light-moderate"));
+    assert_eq!(m.get("turbulence_frequency").map(String::as_str), Some("occasional"));
+    assert_eq!(m.get("turbulence_type").map(String::as_str), Some("chop"));
+    assert_eq!(m.get("turbulence_top_ft").map(String::as_str), Some("8000"));
+    assert_eq!(m.get("icing_intensity").map(String::as_str), Some("light"));
+    assert_eq!(m.get("icing_type").map(String::as_str), Some("rime"));
+    assert_eq!(m.get("icing_top_ft").map(String::as_str), Some("7000"));
+    assert_eq!(m.get("remarks").map(String::as_str), Some("SMOOTH ABV/BLO"));
+    assert!(!m.contains_key("unparsed"));
+}
+
+#[test]
+fn decode_pirep_urgent_unknown_level(){
+    let m = decode_pirep("DEN UUA /OV DEN270020/TM 0105/FLUNKN/TP B737/TB SEV CAT/IC MOD-SEV MX 110-150").unwrap();
+    assert_eq!(m.get("is_urgent").map(String::as_str), Some("true"));
+    assert_eq!(m.get("flight_level_missing").map(String::as_str), Some("true"));
+    assert_eq!(m.get("turbulence_type").map(String::as_str), Some("clear air"));
+    assert_eq!(m.get("icing_intensity").map(String::as_str), Some("moderate-severe"));
+    assert_eq!(m.get("icing_base_ft").map(String::as_str), Some("11000"));
+}
+
+//non-ascii location must not panic
+#[test]
+fn decode_pirep_non_ascii_location(){
+    let m = decode_pirep("UA /OV éééééa/TM 1522/FL085/TP C172").unwrap();
+    assert_eq!(m.get("location").map(String::as_str), Some("éééééa"));
+    assert!(!m.contains_key("location_navaid"));
+    let csv = convert_to_csv(&json!({ "p": "UA /OV éééééa/TM 1522/FL085/TP C172" })).unwrap();
+    assert!(csv.contains("p.pirep.location"));
+}
+
+//bare airline code is not a pirep
+#[test]
+fn csv_pirep_needs_location_and_time(){
+    let csv = convert_to_csv(&json!({ "carrier": "UA" })).unwrap();
+    assert_eq!(csv, "carrier\nUA\n");
+}
+
+#[test]
+fn csv_pirep_columns(){
+    let data = json!([{ "rawOb": "UA /OV OKC063015/TM 1522/FL085/TP C172/TB NEG" }]);
+    let csv = convert_to_csv(&data).unwrap();
+    assert!(csv.contains("rawOb.pirep.aircraft_type"));
+    assert!(csv.contains("rawOb.pirep.turbulence_intensity"));
+    assert!(!csv.contains("rawOb.metar."));
+}
+
+//positional airep goes to same pirep columns
+#[test]
+fn decode_airep(){
+    let m = decode_pirep("ARP UAL902 4430N 16000W 0141 F380 MS51 257/063 TURB MOD CAT ICE LGT RIME").unwrap();
+    assert_eq!(m.get("report_type").map(String::as_str), Some("ARP"));
+    assert_eq!(m.get("is_urgent").map(String::as_str), Some("false"));
+    assert_eq!(m.get("aircraft_id").map(String::as_str), Some("UAL902"));
+    assert_eq!(m.get("latitude").map(String::as_str), Some("44.50"));
+    assert_eq!(m.get("longitude").map(String::as_str), Some("-160.00"));
+    assert_eq!(m.get("obs_hour").map(String::as_str), Some("01"));
+    assert_eq!(m.get("altitude_ft").map(String::as_str), Some("38000"));
+    assert_eq!(m.get("temp_c").map(String::as_str), Some("-51"));
+    assert_eq!(m.get("wind_direction").map(String::as_str), Some("257"));
+    assert_eq!(m.get("wind_speed_kt").map(String::as_str), Some("63"));
+    assert_eq!(m.get("turbulence_intensity").map(String::as_str), Some("moderate"));
+    assert_eq!(m.get("turbulence_type").map(String::as_str), Some("clear air"));
+    assert_eq!(m.get("icing_type").map(String::as_str), Some("rime"));
+    assert!(!m.contains_key("unparsed"));
+    let m = decode_pirep("ARS BAW123 DOGAL 1522 FL370 PS02 TB SEV").unwrap();
+    assert_eq!(m.get("is_urgent").map(String::as_str), Some("true"));
+    assert_eq!(m.get("location").map(String::as_str), Some("DOGAL"));
+    assert_eq!(m.get("turbulence_intensity").map(String::as_str), Some("severe"));
+    assert!(decode_pirep("ARP").is_none());
+}